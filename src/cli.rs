@@ -1,4 +1,4 @@
-use crate::{git, switch, whoami};
+use crate::{completions, git, prompt, shell, switch, whoami};
 use clap::{arg, command, ArgAction, Command};
 
 pub fn configure_cli() -> Command {
@@ -7,4 +7,7 @@ pub fn configure_cli() -> Command {
         .subcommand(switch::configure_command())
         .subcommand(whoami::configure_command())
         .subcommand(git::configure())
+        .subcommand(shell::configure_command())
+        .subcommand(prompt::configure_command())
+        .subcommand(completions::configure_command())
 }
@@ -0,0 +1,52 @@
+use crate::cli;
+use crate::config::LazyConfig;
+use crate::service::SERVICES;
+use clap::builder::PossibleValuesParser;
+use clap::{arg, ArgMatches, Command};
+use clap_complete::{generate, Shell};
+use std::io::stdout;
+use std::str::FromStr;
+
+pub fn configure_command() -> Command {
+    Command::new("completions")
+        .about("Print shell completions, including the identities from your config")
+        .arg(
+            arg!(<SHELL> "The shell to print completions for")
+                .value_parser(["bash", "zsh", "fish", "elvish"]),
+        )
+}
+
+pub fn run_completions(config: &mut LazyConfig, arg_matches: &ArgMatches) -> anyhow::Result<()> {
+    let shell = Shell::from_str(arg_matches.get_one::<String>("SHELL").unwrap())
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+    // Completions are still useful without identities, so don't fail if the config can't be loaded
+    let identity_ids: Vec<String> = match config.required() {
+        Ok(()) => config.identity.iter().map(|ic| ic.id.clone()).collect(),
+        Err(_) => vec![],
+    };
+
+    let mut command = with_completion_values(cli::configure_cli(), &identity_ids);
+    generate(shell, &mut command, env!("CARGO_BIN_NAME"), &mut stdout());
+
+    Ok(())
+}
+
+/// The values are only set for generating completions, so that the normal CLI reports unknown identities and
+/// services itself.
+fn with_completion_values(command: Command, identity_ids: &[String]) -> Command {
+    let subcommand_names: Vec<String> = command
+        .get_subcommands()
+        .map(|c| c.get_name().to_string())
+        .collect();
+
+    let command = command.mut_args(|a| match a.get_id().as_str() {
+        "identity" => a.value_parser(PossibleValuesParser::new(identity_ids.to_vec())),
+        "service" => a.value_parser(SERVICES),
+        _ => a,
+    });
+
+    subcommand_names.iter().fold(command, |c, name| {
+        c.mut_subcommand(name, |sc| with_completion_values(sc, identity_ids))
+    })
+}
@@ -1,42 +1,119 @@
 use crate::identity::Identity;
 use anyhow::{anyhow, Context};
+use glob::{MatchOptions, Pattern};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
 use std::fs::{create_dir, File};
 use std::io::{Read, Write};
 use std::ops::Deref;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
     pub version: String,
+    pub strict: Option<bool>,
+    pub git: Option<GitConfig>,
     pub identity: Vec<IdentityConfig>,
 }
 
 impl Config {
-    pub fn account_for_url(&self, service: &str, url: &str) -> anyhow::Result<Identity> {
-        let candidate_identities: Vec<Identity> = self
+    pub fn account_for_repository(
+        &self,
+        service: &str,
+        url: &str,
+        path: Option<&Path>,
+    ) -> anyhow::Result<Identity<'_>> {
+        let mut candidate_identities =
+            self.candidate_identities(service, |ac| ac.account_matches_url(url));
+
+        // URL rules are more specific than path rules, so paths are only used when no URL matches
+        if let (true, Some(path)) = (candidate_identities.is_empty(), path) {
+            candidate_identities =
+                self.candidate_identities(service, |ac| ac.account_matches_path(path));
+        }
+
+        if candidate_identities.is_empty() && !self.strict.unwrap_or(false) {
+            candidate_identities = self.default_identities(service)?;
+        }
+
+        match candidate_identities.len() {
+            0 => match path {
+                Some(path) => Err(anyhow!(
+                    "No identity found for URL - {} or path - {:?}",
+                    url,
+                    path
+                )),
+                None => Err(anyhow!("No identity found for URL - {}", url)),
+            },
+            1 => Ok(candidate_identities.first().unwrap().clone()),
+            _ => Err(anyhow!(
+                "Multiple identities found for URL - {:?}",
+                candidate_identities
+            )),
+        }
+    }
+
+    pub fn account_for_url(&self, service: &str, url: &str) -> anyhow::Result<Identity<'_>> {
+        self.account_for_repository(service, url, None)
+    }
+
+    /// The accounts whose `match_url` matches, without falling back to a default identity
+    pub fn identities_for_url(&self, service: &str, url: &str) -> Vec<Identity<'_>> {
+        self.candidate_identities(service, |ac| ac.account_matches_url(url))
+    }
+
+    /// Every account for the service, whatever it matches
+    pub fn identities_for_service(&self, service: &str) -> Vec<Identity<'_>> {
+        self.candidate_identities(service, |_| true)
+    }
+
+    /// The hook mode from the command line, or failing that from the `[git]` table
+    pub fn hook_mode(&self, mode: Option<HookMode>) -> HookMode {
+        mode.or_else(|| self.git.as_ref().and_then(|g| g.hook_mode))
+            .unwrap_or_default()
+    }
+
+    fn default_identities(&self, service: &str) -> anyhow::Result<Vec<Identity<'_>>> {
+        let default_identities: Vec<Identity> = self
             .identity
             .iter()
             .flat_map(|ic| {
                 let inner: Vec<Identity> = ic
-                    .accounts_for_url(service, url)
+                    .accounts_matching(service, |ac| ac.default.or(ic.default).unwrap_or(false))
                     .iter()
-                    .map(|ac| Identity::from(ic, ac))
+                    .map(|ac| Identity::from(ic, ac).into_default())
                     .collect();
                 inner
             })
             .collect();
 
-        match candidate_identities.len() {
-            0 => Err(anyhow!("No identity found for URL - {}", url)),
-            1 => Ok(candidate_identities.first().unwrap().clone()),
-            _ => Err(anyhow!(
-                "Multiple identities found for URL - {:?}",
-                candidate_identities
-            )),
+        if default_identities.len() > 1 {
+            return Err(anyhow!(
+                "Multiple default identities found for service {} - {:?}",
+                service,
+                default_identities
+            ));
         }
+
+        Ok(default_identities)
+    }
+
+    fn candidate_identities<F>(&self, service: &str, predicate: F) -> Vec<Identity<'_>>
+    where
+        F: Fn(&AccountConfig) -> bool,
+    {
+        self.identity
+            .iter()
+            .flat_map(|ic| {
+                let inner: Vec<Identity> = ic
+                    .accounts_matching(service, &predicate)
+                    .iter()
+                    .map(|ac| Identity::from(ic, ac))
+                    .collect();
+                inner
+            })
+            .collect()
     }
 }
 
@@ -46,11 +123,12 @@ pub struct IdentityConfig {
     pub user: Option<String>,
     pub email: Option<String>,
     pub description: Option<String>,
+    pub default: Option<bool>,
     pub account: Option<Vec<AccountConfig>>,
 }
 
 impl IdentityConfig {
-    pub fn identity_for_service(&self, service: &str) -> anyhow::Result<Option<Identity>> {
+    pub fn identity_for_service(&self, service: &str) -> anyhow::Result<Option<Identity<'_>>> {
         if let Some(account) = &self.account {
             let accounts: Vec<&AccountConfig> =
                 account.iter().filter(|ac| ac.service == service).collect();
@@ -69,11 +147,14 @@ impl IdentityConfig {
         }
     }
 
-    fn accounts_for_url(&self, service: &str, url: &str) -> Vec<&AccountConfig> {
+    fn accounts_matching<F>(&self, service: &str, predicate: F) -> Vec<&AccountConfig>
+    where
+        F: Fn(&AccountConfig) -> bool,
+    {
         if let Some(account) = &self.account {
             account
                 .iter()
-                .filter(|a| a.service == service && a.account_matches_url(url))
+                .filter(|a| a.service == service && predicate(a))
                 .collect()
         } else {
             vec![]
@@ -86,28 +167,140 @@ pub struct AccountConfig {
     pub service: String,
     pub user: Option<String>,
     pub match_url: Option<String>,
+    pub match_path: Option<String>,
     pub description: Option<String>,
     pub token: Option<String>,
+    pub ssh_key: Option<String>,
+    pub signing_key: Option<String>,
+    pub signing_format: Option<SigningFormat>,
+    pub default: Option<bool>,
 }
 
 impl AccountConfig {
     fn account_matches_url(&self, url: &str) -> bool {
         match &self.match_url {
-            Some(match_url) => {
-                if match_url.ends_with('*') {
-                    let mut url_prefix = match_url.clone();
-                    url_prefix.pop();
-
-                    url.contains(&url_prefix)
-                } else {
-                    url == match_url
-                }
-            }
+            Some(match_url) => match_url_patterns(match_url)
+                .iter()
+                .any(|pattern| url_matches_pattern(pattern, url)),
+            None => false,
+        }
+    }
+
+    fn account_matches_path(&self, path: &Path) -> bool {
+        match &self.match_path {
+            Some(match_path) => match_path_patterns(match_path).iter().any(|pattern| {
+                Pattern::new(pattern)
+                    .map(|p| p.matches_path_with(path, PATH_MATCH_OPTIONS))
+                    .unwrap_or(false)
+            }),
             None => false,
         }
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SigningFormat {
+    Gpg,
+    Ssh,
+    X509,
+}
+
+impl SigningFormat {
+    /// The value Git expects for `gpg.format`
+    pub fn git_format(&self) -> &'static str {
+        match self {
+            SigningFormat::Gpg => "openpgp",
+            SigningFormat::Ssh => "ssh",
+            SigningFormat::X509 => "x509",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GitConfig {
+    pub hook_mode: Option<HookMode>,
+    /// Remote names in the order they're used to match an identity, `origin` alone when not set
+    pub remotes: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HookMode {
+    /// Reject the commit, leaving the user to switch identity
+    #[default]
+    Fail,
+    /// Report the mismatch but let the commit through
+    Warn,
+    /// Switch to the matched identity, the commit has to be retried to pick it up
+    Fix,
+}
+
+pub const HOOK_MODES: [&str; 3] = ["fail", "warn", "fix"];
+
+impl HookMode {
+    pub fn from_name(name: &str) -> anyhow::Result<HookMode> {
+        match name {
+            "fail" => Ok(HookMode::Fail),
+            "warn" => Ok(HookMode::Warn),
+            "fix" => Ok(HookMode::Fix),
+            _ => Err(anyhow!("Unknown hook mode {}", name)),
+        }
+    }
+}
+
+/// `*` in a URL pattern doesn't cross a `/`, the same as Git's `hasconfig:remote.*.url:`
+const URL_MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// The globs a `match_url` rule stands for, written so that Git's `hasconfig:remote.*.url:` matches exactly the same URLs.
+/// A rule ending in `*` matches any URL containing the rest of the rule, anything else has to match the whole URL.
+pub fn match_url_patterns(match_url: &str) -> Vec<String> {
+    let prefix = match match_url.strip_suffix('*') {
+        Some(prefix) => prefix,
+        None => return vec![Pattern::escape(match_url)],
+    };
+
+    // `**/*` lets the rule start anywhere in the URL, `https://` and all
+    let pattern = format!("**/*{}", Pattern::escape(prefix));
+    if prefix.ends_with('/') {
+        vec![format!("{}**", pattern)]
+    } else {
+        // The rule ends part way through a path segment, which may or may not be the last one
+        vec![format!("{}*", pattern), format!("{}*/**", pattern)]
+    }
+}
+
+/// Match a URL against a pattern from `match_url_patterns` or a `hasconfig:remote.*.url:` condition
+pub fn url_matches_pattern(pattern: &str, url: &str) -> bool {
+    Pattern::new(pattern)
+        .map(|p| p.matches_with(url, URL_MATCH_OPTIONS))
+        .unwrap_or(false)
+}
+
+/// `*` in a path pattern doesn't cross a `/`, the same as Git's `gitdir:`
+const PATH_MATCH_OPTIONS: MatchOptions = URL_MATCH_OPTIONS;
+
+/// The globs a `match_path` rule stands for. Git's `gitdir:` with a trailing `/` matches everything below the path, so
+/// repositories nested anywhere under a matching directory match too, not just the directory itself.
+fn match_path_patterns(match_path: &str) -> [String; 2] {
+    let match_path = expand_home(match_path);
+    let match_path = match_path.trim_end_matches('/');
+
+    [match_path.to_string(), format!("{}/**", match_path)]
+}
+
+/// Replace a leading `~` with the home directory, paths in the config are usually written that way.
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix('~'), home::home_dir()) {
+        (Some(rest), Some(home_path)) => format!("{}{}", home_path.display(), rest),
+        _ => path.to_string(),
+    }
+}
+
 pub struct LazyConfig {
     config: Option<Config>,
 }
@@ -178,7 +371,7 @@ where
     Ok(updated_config)
 }
 
-fn get_config_path() -> anyhow::Result<PathBuf> {
+pub fn get_config_path() -> anyhow::Result<PathBuf> {
     let home_path = match home::home_dir() {
         Some(p) => p,
         None => return Err(anyhow!("Unable to find your home directory")),
@@ -203,6 +396,8 @@ fn create_default_config(config_path: &PathBuf) -> anyhow::Result<()> {
         .with_context(|| format!("Failed to open config at - {:?}", config_path))?;
     let new_config = Config {
         version: "1.0".to_string(),
+        strict: None,
+        git: None,
         identity: vec![],
     };
     let content = toml::to_string(&new_config).with_context(|| {
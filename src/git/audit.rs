@@ -0,0 +1,137 @@
+use crate::config::LazyConfig;
+use crate::git::backend::get_backend;
+use crate::git::commits::{identity_mismatches, list_commits};
+use crate::git::resolve::resolve_identity;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::process::exit;
+
+#[derive(Serialize)]
+struct AuditReport {
+    identity: String,
+    expected: String,
+    offenders: Vec<Offender>,
+}
+
+/// Someone other than the matched identity who authored or committed commits in the audited range
+#[derive(Serialize)]
+struct Offender {
+    name: String,
+    email: String,
+    /// Set when the offender is one of the other identities in the config, which usually means a leak
+    identity: Option<String>,
+    commits: Vec<OffendingCommit>,
+}
+
+#[derive(Serialize)]
+struct OffendingCommit {
+    sha: String,
+    role: &'static str,
+    summary: String,
+}
+
+pub fn run_git_audit(
+    config: &mut LazyConfig,
+    since: Option<&String>,
+    branch: bool,
+    json: bool,
+) -> anyhow::Result<()> {
+    config.required()?;
+
+    let identity = resolve_identity(config, get_backend().as_ref())?;
+
+    let range = since.map(|since| format!("{}..HEAD", since));
+    let mut revision_args = vec![range.as_deref().unwrap_or("HEAD")];
+    if branch {
+        // Only what hasn't been pushed anywhere yet
+        revision_args.extend(["--not", "--remotes"]);
+    }
+
+    let mut offenders: BTreeMap<(String, String), Offender> = BTreeMap::new();
+    for commit in list_commits(&revision_args)? {
+        for role in identity_mismatches(&identity, &commit) {
+            let (name, email) = match role {
+                "author" => (&commit.author_name, &commit.author_email),
+                _ => (&commit.committer_name, &commit.committer_email),
+            };
+
+            offenders
+                .entry((email.clone(), name.clone()))
+                .or_insert_with(|| Offender {
+                    name: name.clone(),
+                    email: email.clone(),
+                    identity: config
+                        .identity
+                        .iter()
+                        .find(|ic| ic.email.as_ref() == Some(email))
+                        .map(|ic| ic.id.clone()),
+                    commits: vec![],
+                })
+                .commits
+                .push(OffendingCommit {
+                    sha: commit.sha.clone(),
+                    role,
+                    summary: commit.summary.clone(),
+                });
+        }
+    }
+
+    let report = AuditReport {
+        identity: identity.id().to_string(),
+        expected: format!(
+            "{} <{}>",
+            identity.user().unwrap_or(&"no username".to_string()),
+            identity.email().unwrap_or(&"missing".to_string())
+        ),
+        offenders: offenders.into_values().collect(),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+
+    if !report.offenders.is_empty() {
+        exit(1);
+    }
+
+    Ok(())
+}
+
+fn print_report(report: &AuditReport) {
+    if report.offenders.is_empty() {
+        println!(
+            "All commits match identity `{}` - {}",
+            report.identity, report.expected
+        );
+        return;
+    }
+
+    println!(
+        "Found commits which don't match identity `{}` - {}",
+        report.identity, report.expected
+    );
+    for offender in &report.offenders {
+        let known = offender
+            .identity
+            .as_ref()
+            .map(|id| format!(" (identity `{}`)", id))
+            .unwrap_or_default();
+        println!(
+            "\n{} <{}>{} - {} commit(s)",
+            offender.name,
+            offender.email,
+            known,
+            offender.commits.len()
+        );
+        for commit in &offender.commits {
+            println!(
+                "  {} {:<9} {}",
+                &commit.sha[..commit.sha.len().min(10)],
+                commit.role,
+                commit.summary
+            );
+        }
+    }
+}
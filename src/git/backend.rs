@@ -0,0 +1,159 @@
+use crate::git::in_process::InProcessBackend;
+use std::env;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::process::Command;
+
+/// Set to `subprocess` to always ask `git`, for when the in-process reader gets something wrong
+const BACKEND_ENV: &str = "IDENTITY_GIT_BACKEND";
+
+/// Read-only access to the repository in the current directory and the config Git would use for it.
+/// Values which aren't set come back empty, the same as `git config` prints for them.
+pub trait GitBackend {
+    /// The top level of the working tree, empty when not in a repository
+    fn get_toplevel(&self) -> anyhow::Result<String>;
+
+    fn get_config(&self, key: &str) -> anyhow::Result<String>;
+
+    fn get_bool_config(&self, key: &str) -> anyhow::Result<bool>;
+
+    /// Where the value `get_config` returns was set, `None` when it isn't set anywhere
+    fn get_config_origin(&self, key: &str) -> anyhow::Result<Option<ConfigOrigin>>;
+
+    /// A value from the global config only, ignoring the system and repository config
+    fn get_global_config(&self, key: &str) -> anyhow::Result<String>;
+
+    /// Every config file the values are read from, including the ones they include
+    fn get_config_files(&self) -> anyhow::Result<Vec<String>>;
+
+    /// The names of the repository's remotes, in the order they're configured
+    fn get_remotes(&self) -> anyhow::Result<Vec<String>>;
+
+    /// The remote's URL after any `url.<base>.insteadOf` rewriting
+    fn get_remote_url(&self, remote: &str) -> anyhow::Result<String>;
+
+    /// The checked out branch, empty when HEAD is detached
+    fn get_current_branch(&self) -> anyhow::Result<String>;
+}
+
+/// The scope and file a config value came from, like `git config --show-scope --show-origin` reports
+pub struct ConfigOrigin {
+    /// `system`, `global`, `local` or `worktree`
+    pub scope: String,
+    pub path: String,
+}
+
+impl Display for ConfigOrigin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} config {}", self.scope, self.path)
+    }
+}
+
+/// Pick how to read from Git. Reading the files directly saves starting a `git` process for every value, but anything
+/// the in-process reader doesn't understand is left to `git` itself.
+pub fn get_backend() -> Box<dyn GitBackend> {
+    if env::var(BACKEND_ENV).as_deref() == Ok("subprocess") {
+        return Box::new(SubprocessBackend);
+    }
+
+    match InProcessBackend::discover() {
+        Ok(Some(backend)) => Box::new(backend),
+        _ => Box::new(SubprocessBackend),
+    }
+}
+
+/// Runs `git` for every value
+pub struct SubprocessBackend;
+
+impl SubprocessBackend {
+    fn git_output(args: &[&str]) -> anyhow::Result<String> {
+        Ok(
+            String::from_utf8(Command::new("git").args(args).output()?.stdout)?
+                .trim_end()
+                .to_string(),
+        )
+    }
+}
+
+impl GitBackend for SubprocessBackend {
+    fn get_toplevel(&self) -> anyhow::Result<String> {
+        Self::git_output(&["rev-parse", "--show-toplevel"])
+    }
+
+    fn get_config(&self, key: &str) -> anyhow::Result<String> {
+        Self::git_output(&["config", key])
+    }
+
+    fn get_bool_config(&self, key: &str) -> anyhow::Result<bool> {
+        Ok(Self::git_output(&["config", "--type=bool", key])? == "true")
+    }
+
+    fn get_config_origin(&self, key: &str) -> anyhow::Result<Option<ConfigOrigin>> {
+        let output = Self::git_output(&["config", "--show-scope", "--show-origin", key])?;
+
+        // <scope>\t<origin>\t<value>, where the origin is relative to the top level for the repository's own files
+        let mut parts = output.splitn(3, '\t');
+        let (scope, origin) = match (parts.next(), parts.next()) {
+            (Some(scope), Some(origin)) if !scope.is_empty() => (scope, origin),
+            _ => return Ok(None),
+        };
+
+        let path = match origin.strip_prefix("file:") {
+            Some(path) if Path::new(path).is_relative() => Path::new(self.get_toplevel()?.as_str())
+                .join(path)
+                .display()
+                .to_string(),
+            Some(path) => path.to_string(),
+            None => origin.to_string(),
+        };
+
+        Ok(Some(ConfigOrigin {
+            scope: scope.to_string(),
+            path,
+        }))
+    }
+
+    fn get_global_config(&self, key: &str) -> anyhow::Result<String> {
+        Self::git_output(&["config", "--global", key])
+    }
+
+    fn get_config_files(&self) -> anyhow::Result<Vec<String>> {
+        let toplevel = self.get_toplevel()?;
+
+        let mut files: Vec<String> = vec![];
+        for line in Self::git_output(&["config", "--list", "--show-origin", "--name-only"])?.lines()
+        {
+            let path = match line
+                .split('\t')
+                .next()
+                .and_then(|o| o.strip_prefix("file:"))
+            {
+                Some(path) => Path::new(toplevel.as_str())
+                    .join(path)
+                    .display()
+                    .to_string(),
+                None => continue,
+            };
+            if !files.contains(&path) {
+                files.push(path);
+            }
+        }
+
+        Ok(files)
+    }
+
+    fn get_remotes(&self) -> anyhow::Result<Vec<String>> {
+        Ok(Self::git_output(&["remote"])?
+            .lines()
+            .map(|l| l.to_string())
+            .collect())
+    }
+
+    fn get_remote_url(&self, remote: &str) -> anyhow::Result<String> {
+        Self::git_output(&["remote", "get-url", remote])
+    }
+
+    fn get_current_branch(&self) -> anyhow::Result<String> {
+        Self::git_output(&["branch", "--show-current"])
+    }
+}
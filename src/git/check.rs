@@ -1,49 +1,231 @@
-use crate::config::LazyConfig;
-use crate::git::common::{get_credentials_helper, get_origin_url};
+use crate::config::{Config, LazyConfig};
+use crate::git::backend::{get_backend, GitBackend};
+use crate::git::common::get_credentials_helper;
 use crate::git::credentials::get_current_credential;
-use crate::git::hook::run_git_pre_commit_hook;
-use crate::git::install::get_pre_commit_hook_path;
-use crate::git::GIT_SERVICE;
+use crate::git::framework::{get_registered_framework, Framework};
+use crate::git::hook::get_config_mismatches;
+use crate::git::install::{get_hook_block, get_hook_path, HOOK_BLOCK_START, PRE_COMMIT};
+use crate::git::resolve::{get_remote_conflicts, resolve_identity, resolve_identity_and_url};
+use crate::git::ssh::check_ssh_key;
+use crate::git::submodules::for_each_submodule;
+use crate::identity::Identity;
 use anyhow::{anyhow, Context};
 use regex::Regex;
 use std::fs::File;
 use std::io::Read;
 use std::process::{Command, Stdio};
 
-pub fn run_git_check(config: &mut LazyConfig) -> anyhow::Result<()> {
-    let git_version = check_git().with_context(|| "Git not found")?;
+#[derive(PartialEq)]
+enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
 
-    let git_version_okay =
-        check_git_version(git_version.as_str()).with_context(|| "Failed to check Git version")?;
-    if !git_version_okay {
-        return Err(anyhow!("Unsupported Git version - {}", git_version));
+struct CheckResult {
+    name: &'static str,
+    status: CheckStatus,
+    message: String,
+    /// What to do about a warning or failure
+    hint: Option<&'static str>,
+}
+
+impl CheckResult {
+    fn pass(name: &'static str, message: String) -> Self {
+        CheckResult {
+            name,
+            status: CheckStatus::Pass,
+            message,
+            hint: None,
+        }
     }
 
-    if get_credentials_helper()?.is_empty() {
-        return Err(anyhow!("No credentials helper configured"));
+    fn warn(name: &'static str, message: String, hint: &'static str) -> Self {
+        CheckResult {
+            name,
+            status: CheckStatus::Warn,
+            message,
+            hint: Some(hint),
+        }
     }
 
-    check_hook_content()?;
+    fn fail(name: &'static str, message: String, hint: &'static str) -> Self {
+        CheckResult {
+            name,
+            status: CheckStatus::Fail,
+            message,
+            hint: Some(hint),
+        }
+    }
+}
 
-    check_credentials(config)?;
+pub fn run_git_check(config: &mut LazyConfig) -> anyhow::Result<()> {
+    config.required()?;
+
+    let mut results = collect_checks(config, get_backend().as_ref());
+    results.extend(check_submodules(config));
+
+    for result in &results {
+        let label = match result.status {
+            CheckStatus::Pass => "pass",
+            CheckStatus::Warn => "warn",
+            CheckStatus::Fail => "fail",
+        };
+        println!("[{}] {} - {}", label, result.name, result.message);
+        if let Some(hint) = result.hint {
+            println!("       {}", hint);
+        }
+    }
 
-    run_git_pre_commit_hook(config)?;
+    let failures = results
+        .iter()
+        .filter(|r| r.status == CheckStatus::Fail)
+        .count();
+    if failures > 0 {
+        return Err(anyhow!("{} of {} checks failed", failures, results.len()));
+    }
 
     println!("Everything looks good!");
 
     Ok(())
 }
 
-fn check_credentials(config: &mut LazyConfig) -> anyhow::Result<()> {
-    config.required()?;
+/// Run every check, each one is independent so a failure doesn't hide the others
+fn collect_checks(config: &LazyConfig, backend: &dyn GitBackend) -> Vec<CheckResult> {
+    let mut results = vec![check_git_installation(), check_credentials_helper(backend)];
+
+    results.push(match check_hook_content(backend) {
+        Ok(()) => CheckResult::pass("Pre-commit hook", "Contains the identity check".to_string()),
+        Err(e) => CheckResult::fail(
+            "Pre-commit hook",
+            e.to_string(),
+            "Run `identity git install` to add the identity check",
+        ),
+    });
+
+    results.push(check_remotes(config, backend));
+
+    // The rest need to know which identity the repository should use
+    let (identity, url) = match resolve_identity_and_url(config, backend) {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            results.push(CheckResult::fail(
+                "Identity",
+                e.to_string(),
+                "Add a `match_url` or `match_path` rule for this repository to your identity.toml",
+            ));
+            return results;
+        }
+    };
+
+    let description = format!(
+        "`{}` - {} <{}>",
+        identity.id(),
+        identity.user().unwrap_or(&"no username".to_string()),
+        identity.email().unwrap_or(&"missing".to_string())
+    );
+    results.push(if identity.is_default() {
+        CheckResult::warn(
+            "Identity",
+            format!(
+                "No identity matched, using default identity {}",
+                description
+            ),
+            "Add a `match_url` or `match_path` rule if this repository shouldn't use the default",
+        )
+    } else {
+        CheckResult::pass("Identity", format!("Matched {}", description))
+    });
+
+    results.push(match check_credentials(backend, &identity, url.as_str()) {
+        Ok(()) if identity.token().is_none() => {
+            CheckResult::pass("Credentials", "No token configured".to_string())
+        }
+        Ok(()) => CheckResult::pass("Credentials", "Token matches".to_string()),
+        Err(e) => CheckResult::fail(
+            "Credentials",
+            e.to_string(),
+            "Run `identity git sync-credentials` to store the configured token",
+        ),
+    });
+
+    results.push(match check_ssh_key(backend, &identity) {
+        Ok(()) if identity.ssh_key().is_none() => {
+            CheckResult::pass("SSH key", "None configured".to_string())
+        }
+        Ok(()) => CheckResult::pass("SSH key", "Configured correctly".to_string()),
+        Err(e) => CheckResult::fail(
+            "SSH key",
+            e.to_string(),
+            "Fix the key's permissions or run `identity switch --service git`",
+        ),
+    });
+
+    results.push(match get_config_mismatches(backend, &identity) {
+        Ok(mismatches) if mismatches.is_empty() => {
+            CheckResult::pass("Git config", "Matches the identity".to_string())
+        }
+        Ok(mismatches) => CheckResult::fail(
+            "Git config",
+            mismatches.join(", "),
+            "Run `identity switch --service git` to apply the identity",
+        ),
+        Err(e) => CheckResult::fail(
+            "Git config",
+            e.to_string(),
+            "Run `identity switch --service git` to apply the identity",
+        ),
+    });
+
+    results
+}
 
-    let origin = get_origin_url()?;
-    let identity = config.account_for_url(GIT_SERVICE, origin.as_str())?;
+fn check_git_installation() -> CheckResult {
+    let git_version = match check_git() {
+        Ok(version) => version.trim_end().to_string(),
+        Err(_) => return CheckResult::fail("Git", "Git not found".to_string(), "Install Git 2"),
+    };
 
+    match check_git_version(git_version.as_str()) {
+        Ok(true) => CheckResult::pass("Git", git_version),
+        Ok(false) => CheckResult::fail(
+            "Git",
+            format!("Unsupported Git version - {}", git_version),
+            "Install Git 2",
+        ),
+        Err(e) => CheckResult::fail(
+            "Git",
+            format!("Failed to check Git version - {}", e),
+            "Install Git 2",
+        ),
+    }
+}
+
+fn check_credentials_helper(backend: &dyn GitBackend) -> CheckResult {
+    match get_credentials_helper(backend) {
+        Ok(helper) if !helper.is_empty() => CheckResult::pass("Credentials helper", helper),
+        Ok(_) => CheckResult::fail(
+            "Credentials helper",
+            "No credentials helper configured".to_string(),
+            "Set one with `git config --global credential.helper`, `identity git credential` will do",
+        ),
+        Err(e) => CheckResult::fail(
+            "Credentials helper",
+            e.to_string(),
+            "Set one with `git config --global credential.helper`, `identity git credential` will do",
+        ),
+    }
+}
+
+fn check_credentials(
+    backend: &dyn GitBackend,
+    identity: &Identity,
+    url: &str,
+) -> anyhow::Result<()> {
     let token = identity.token();
 
     if let Some(configured_token) = token {
-        let actual_token = get_current_credential(&identity)?;
+        let actual_token = get_current_credential(backend, identity, url)?;
 
         if configured_token.as_str() != actual_token.as_str() {
             return Err(anyhow!(
@@ -55,8 +237,114 @@ fn check_credentials(config: &mut LazyConfig) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn check_hook_content() -> anyhow::Result<()> {
-    let pre_commit_hook_path = get_pre_commit_hook_path()?;
+/// Each submodule is matched by its own remotes, so it can need a different identity to the superproject
+fn check_submodules(config: &Config) -> Vec<CheckResult> {
+    match for_each_submodule(|submodule| Ok(check_submodule(config, submodule))) {
+        Ok(results) => results,
+        Err(e) => vec![CheckResult::fail(
+            "Submodule",
+            e.to_string(),
+            "Run `git submodule update --init --recursive` to check out the submodules",
+        )],
+    }
+}
+
+fn check_submodule(config: &Config, submodule: &str) -> CheckResult {
+    let backend = get_backend();
+    let identity = match resolve_identity(config, backend.as_ref()) {
+        Ok(identity) => identity,
+        Err(e) => {
+            return CheckResult::fail(
+                "Submodule",
+                format!("`{}` - {}", submodule, e),
+                "Add a `match_url` rule for the submodule's remote to your identity.toml",
+            )
+        }
+    };
+
+    let mismatches = match get_config_mismatches(backend.as_ref(), &identity) {
+        Ok(mismatches) => mismatches,
+        Err(e) => vec![e.to_string()],
+    };
+    if !mismatches.is_empty() {
+        return CheckResult::fail(
+            "Submodule",
+            format!(
+                "`{}` should use `{}` - {}",
+                submodule,
+                identity.id(),
+                mismatches.join(", ")
+            ),
+            "Run `identity switch --service git --recurse-submodules` to apply each submodule's identity",
+        );
+    }
+
+    if check_hook_content(backend.as_ref()).is_err() {
+        return CheckResult::warn(
+            "Submodule",
+            format!(
+                "`{}` uses `{}` but has no identity check in its pre-commit hook",
+                submodule,
+                identity.id()
+            ),
+            "Run `identity git install --recurse-submodules` to add the identity check",
+        );
+    }
+
+    CheckResult::pass(
+        "Submodule",
+        format!("`{}` uses `{}`", submodule, identity.id()),
+    )
+}
+
+fn check_remotes(config: &Config, backend: &dyn GitBackend) -> CheckResult {
+    let conflicts = match get_remote_conflicts(config, backend) {
+        Ok(conflicts) => conflicts,
+        Err(e) => {
+            return CheckResult::fail(
+                "Remotes",
+                e.to_string(),
+                "Check `git remote -v` lists valid remotes",
+            )
+        }
+    };
+
+    match conflicts.first() {
+        Some((remote, id)) => {
+            let matches: Vec<String> = conflicts
+                .iter()
+                .map(|(remote, id)| format!("{}=`{}`", remote, id))
+                .collect();
+            CheckResult::warn(
+                "Remotes",
+                format!(
+                    "Remotes match different identities - {}, using `{}` from `{}`",
+                    matches.join(", "),
+                    id,
+                    remote
+                ),
+                "Set `remotes` in the `[git]` table of your identity.toml to choose which remote decides",
+            )
+        }
+        None => CheckResult::pass("Remotes", "No conflicting identities".to_string()),
+    }
+}
+
+pub fn check_hook_content(backend: &dyn GitBackend) -> anyhow::Result<()> {
+    let framework = get_registered_framework(backend)?;
+
+    let pre_commit_hook_path = get_hook_path(PRE_COMMIT.name)?;
+    if !pre_commit_hook_path.exists() {
+        return match framework {
+            Some(f) => Err(anyhow!(
+                "The identity check is registered with {} but its hooks aren't installed, run `{} install`",
+                f.name(),
+                f.name()
+            )),
+            None => Err(anyhow!("Pre-commit hook not found")),
+        };
+    }
+
     let mut f = File::open(&pre_commit_hook_path).with_context(|| "Pre-commit hook not found")?;
     let mut content = String::new();
     f.read_to_string(&mut content).with_context(|| {
@@ -65,7 +353,34 @@ fn check_hook_content() -> anyhow::Result<()> {
             pre_commit_hook_path
         )
     })?;
-    if !content.contains("identity git hook --pre-commit") {
+    if content.contains(HOOK_BLOCK_START) {
+        match get_hook_block(content.as_str()) {
+            Some(block) if block.iter().any(|l| l.contains(PRE_COMMIT.command)) => {}
+            _ => {
+                return Err(anyhow!(
+                    "Pre-commit hook has a damaged identity check, run `identity git install` to repair it"
+                ))
+            }
+        }
+    } else if content.contains(PRE_COMMIT.command) {
+        // Installed by an older version, before the check was wrapped in a block
+    } else if let Some(f) = Framework::from_hook(content.as_str()) {
+        // The framework's hook runs the check from its own config
+        if !f.is_registered(backend)? {
+            return Err(anyhow!(
+                "The pre-commit hook is managed by {} but the identity check isn't registered with it, run `identity git install --framework {}`",
+                f.name(),
+                f.name()
+            ));
+        }
+    } else if let Some(f) = framework {
+        return Err(anyhow!(
+            "The identity check is registered with {} but the pre-commit hook wasn't installed by {}, run `{} install`",
+            f.name(),
+            f.name(),
+            f.name()
+        ));
+    } else {
         return Err(anyhow!(
             "Pre-commit hook does not contain an identity check"
         ));
@@ -96,7 +411,8 @@ pub fn check_is_git_repository() -> anyhow::Result<()> {
         .args(["rev-parse", "--show-toplevel"])
         .stdout(Stdio::null())
         .stderr(Stdio::null())
-        .spawn()?.wait()?;
+        .spawn()?
+        .wait()?;
 
     if !code.success() {
         return Err(anyhow!("Not in a Git repository"));
@@ -1,3 +1,4 @@
+use crate::config::HOOK_MODES;
 use clap::{arg, ArgAction, Command};
 
 pub fn configure() -> Command {
@@ -6,7 +7,89 @@ pub fn configure() -> Command {
         .arg(arg!(-c --check "Check configuration").action(ArgAction::SetTrue))
         .subcommand(
             Command::new("install")
-                .arg(arg!(--force "Force the operation to proceed").action(ArgAction::SetTrue)),
+                .arg(arg!(--force "Force the operation to proceed").action(ArgAction::SetTrue))
+                .arg(
+                    arg!(--"pre-push" "Also install a pre-push hook which checks every commit being pushed")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    arg!(--"commit-msg" "Also install a commit-msg hook which checks the Signed-off-by trailers")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    arg!(--recursive <DIR> "Install in every repository under a directory and apply its identity")
+                        .action(ArgAction::Set)
+                        .conflicts_with("framework"),
+                )
+                .arg(
+                    arg!(--"recurse-submodules" "Also install in every checked out submodule")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with_all(["framework", "recursive"]),
+                )
+                .arg(
+                    arg!(--framework "Register the check with a hook framework instead of writing the hook")
+                        .action(ArgAction::Set)
+                        .value_name("FRAMEWORK")
+                        .value_parser(["pre-commit", "lefthook"])
+                        .conflicts_with("force"),
+                ),
+        )
+        .subcommand(
+            Command::new("check")
+                .about("Check configuration, the same as `--check`")
+                .arg(
+                    arg!(--recursive <DIR> "Check every repository under a directory and print a summary")
+                        .action(ArgAction::Set),
+                ),
+        )
+        .subcommand(
+            Command::new("uninstall")
+                .about("Remove the identity check from the pre-commit hook, leaving the rest of the hook alone"),
+        )
+        .subcommand(
+            Command::new("sync-global")
+                .about("Write conditional includes for your identities into your global Git config"),
+        )
+        .subcommand(
+            Command::new("audit")
+                .about("Report commits whose author or committer doesn't match the identity for this repository")
+                .arg(
+                    arg!(--since <REVISION> "Only audit commits made after this revision")
+                        .action(ArgAction::Set),
+                )
+                .arg(
+                    arg!(--branch "Only audit commits on the current branch which haven't been pushed")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(arg!(--json "Print the report as JSON").action(ArgAction::SetTrue)),
+        )
+        .subcommand(
+            Command::new("fix-history")
+                .about("Rewrite unpushed commits made as your other identities and map pushed ones in .mailmap")
+                .arg(arg!(--"dry-run" "Show what would be changed without changing it").action(ArgAction::SetTrue))
+                .arg(
+                    arg!(--yes "Make the changes without asking first")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("dry-run"),
+                ),
+        )
+        .subcommand(
+            Command::new("credential")
+                .about("Act as a Git credential helper, answering with the token of the matched account")
+                .arg(
+                    arg!(<OPERATION> "The operation Git asks the helper to perform")
+                        .value_parser(["get", "store", "erase"]),
+                ),
+        )
+        .subcommand(
+            Command::new("sync-credentials")
+                .about("Store the token of the matched account in your Git credentials helper"),
+        )
+        .subcommand(
+            Command::new("clone")
+                .about("Clone a repository as the identity matched for its URL, ready to commit")
+                .arg(arg!(<URL> "The repository to clone"))
+                .arg(arg!([DIRECTORY] "The directory to clone into")),
         )
         .subcommand(
             Command::new("hook")
@@ -14,6 +97,35 @@ pub fn configure() -> Command {
                     arg!(--"pre-commit" "Behave as a Git pre-commit hook")
                         .action(ArgAction::SetTrue),
                 )
+                .arg(
+                    arg!(--"pre-push" "Behave as a Git pre-push hook, checking every commit being pushed")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    arg!(--"commit-msg" "Behave as a Git commit-msg hook, checking the Signed-off-by trailers")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    arg!(--rewrite "Rewrite mismatched trailers rather than rejecting the commit")
+                        .action(ArgAction::SetTrue)
+                        .requires("commit-msg"),
+                )
+                .arg(
+                    arg!(--chpwd "Behave as a shell hook when the working directory changes")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    arg!(--apply "Apply the matched identity rather than warning about a mismatch")
+                        .action(ArgAction::SetTrue)
+                        .requires("chpwd"),
+                )
+                .arg(
+                    arg!(--mode "What to do when the identity doesn't match, overriding `hook_mode` in the config")
+                        .action(ArgAction::Set)
+                        .value_name("MODE")
+                        .value_parser(HOOK_MODES),
+                )
+                .arg(arg!([HOOK_ARGS] ... "The arguments Git passes to the hook").num_args(0..))
                 .arg_required_else_help(true),
         )
 }
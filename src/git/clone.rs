@@ -0,0 +1,71 @@
+use crate::config::LazyConfig;
+use crate::git::backend::get_backend;
+use crate::git::credentials::sync_credentials;
+use crate::git::install::{run_git_install, PRE_COMMIT};
+use crate::git::ssh::ssh_command_for_key;
+use crate::git::switch::{apply_switch, ConfigScope};
+use crate::git::GIT_SERVICE;
+use anyhow::{anyhow, Context};
+use std::env::set_current_dir;
+use std::process::Command;
+
+pub fn run_git_clone(
+    config: &mut LazyConfig,
+    url: &str,
+    directory: Option<&String>,
+) -> anyhow::Result<()> {
+    config.required()?;
+
+    // There's no repository yet, so only the URL can pick the identity
+    let identity = config.account_for_url(GIT_SERVICE, url)?;
+
+    let directory = match directory {
+        Some(directory) => directory.clone(),
+        None => default_clone_directory(url)?,
+    };
+
+    // Make sure the helper has this account's token before the fetch asks for it
+    sync_credentials(get_backend().as_ref(), &identity, url)?;
+
+    let mut command = Command::new("git");
+    command.arg("clone");
+    // These are needed for the fetch itself, `apply_switch` sets up the rest afterwards
+    if let Some(ssh_key) = identity.ssh_key() {
+        command.args([
+            "-c",
+            format!("core.sshCommand={}", ssh_command_for_key(ssh_key)).as_str(),
+        ]);
+    }
+    if let Some(user) = identity.user() {
+        command.args(["-c", format!("credential.username={}", user).as_str()]);
+    }
+    command.args(["--", url, directory.as_str()]);
+
+    let code = command.spawn()?.wait()?;
+    if !code.success() {
+        return Err(anyhow!("Failed to clone - {}", url));
+    }
+
+    set_current_dir(&directory)
+        .with_context(|| format!("Failed to enter the new clone - {:?}", directory))?;
+
+    apply_switch(get_backend().as_ref(), &identity, ConfigScope::Local)?;
+    run_git_install(&[&PRE_COMMIT], false)?;
+
+    println!("Cloned into `{}` as `{}`", directory, identity.id());
+
+    Ok(())
+}
+
+/// The directory Git would pick, the last part of the URL without `.git`
+fn default_clone_directory(url: &str) -> anyhow::Result<String> {
+    let trimmed = url.trim_end_matches('/');
+    let trimmed = trimmed.strip_suffix(".git").unwrap_or(trimmed);
+
+    trimmed
+        .rsplit(['/', ':'])
+        .next()
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .ok_or_else(|| anyhow!("Unable to choose a directory to clone into - {}", url))
+}
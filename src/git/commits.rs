@@ -0,0 +1,81 @@
+use crate::identity::Identity;
+use anyhow::anyhow;
+use std::process::Command;
+
+/// Who wrote and who committed a commit
+#[derive(Debug, Clone)]
+pub struct CommitIdentity {
+    pub sha: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub committer_name: String,
+    pub committer_email: String,
+    pub summary: String,
+}
+
+const FIELD_SEPARATOR: char = '\x1f';
+
+/// List the commits selected by `git log` with the given revision arguments.
+pub fn list_commits(revision_args: &[&str]) -> anyhow::Result<Vec<CommitIdentity>> {
+    let output = Command::new("git")
+        .args(["log", "--format=%H%x1f%an%x1f%ae%x1f%cn%x1f%ce%x1f%s"])
+        .args(revision_args)
+        .output()?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to list commits - {}",
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        ));
+    }
+
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(FIELD_SEPARATOR).collect();
+            match fields[..] {
+                [sha, author_name, author_email, committer_name, committer_email, summary] => {
+                    Some(CommitIdentity {
+                        sha: sha.to_string(),
+                        author_name: author_name.to_string(),
+                        author_email: author_email.to_string(),
+                        committer_name: committer_name.to_string(),
+                        committer_email: committer_email.to_string(),
+                        summary: summary.to_string(),
+                    })
+                }
+                _ => None,
+            }
+        })
+        .collect())
+}
+
+/// Whether the object exists locally, the remote may have commits that haven't been fetched.
+pub fn commit_exists(sha: &str) -> anyhow::Result<bool> {
+    Ok(Command::new("git")
+        .args(["cat-file", "-e", format!("{}^{{commit}}", sha).as_str()])
+        .output()?
+        .status
+        .success())
+}
+
+pub fn is_zero_sha(sha: &str) -> bool {
+    sha.chars().all(|c| c == '0')
+}
+
+/// The roles, author or committer, in which the commit doesn't match the identity.
+pub fn identity_mismatches(identity: &Identity, commit: &CommitIdentity) -> Vec<&'static str> {
+    let matches = |name: &String, email: &String| {
+        identity.user() == Some(name) && identity.email() == Some(email)
+    };
+
+    let mut mismatches = vec![];
+    if !matches(&commit.author_name, &commit.author_email) {
+        mismatches.push("author");
+    }
+    if !matches(&commit.committer_name, &commit.committer_email) {
+        mismatches.push("committer");
+    }
+
+    mismatches
+}
@@ -1,45 +1,43 @@
-use std::process::Command;
-
-pub fn get_credentials_helper() -> anyhow::Result<String> {
-    Ok(String::from_utf8(
-        Command::new("git")
-            .args(["config", "--global", "credential.helper"])
-            .output()?
-            .stdout,
-    )?
-    .trim_end()
-    .to_string())
-}
-
-pub fn get_origin_url() -> anyhow::Result<String> {
-    Ok(String::from_utf8(
-        Command::new("git")
-            .args(["remote", "get-url", "origin"])
-            .output()?
-            .stdout,
-    )?
-    .trim_end()
-    .to_string())
-}
-
-pub fn get_username() -> anyhow::Result<String> {
-    Ok(String::from_utf8(
-        Command::new("git")
-            .args(["config", "user.name"])
-            .output()?
-            .stdout,
-    )?
-    .trim_end()
-    .to_string())
-}
-
-pub fn get_email() -> anyhow::Result<String> {
-    Ok(String::from_utf8(
-        Command::new("git")
-            .args(["config", "user.email"])
-            .output()?
-            .stdout,
-    )?
-    .trim_end()
-    .to_string())
+use crate::git::backend::{ConfigOrigin, GitBackend};
+
+pub fn get_credentials_helper(backend: &dyn GitBackend) -> anyhow::Result<String> {
+    backend.get_global_config("credential.helper")
+}
+
+pub fn get_toplevel(backend: &dyn GitBackend) -> anyhow::Result<String> {
+    backend.get_toplevel()
+}
+
+pub fn get_username(backend: &dyn GitBackend) -> anyhow::Result<String> {
+    get_config(backend, "user.name")
+}
+
+pub fn get_email(backend: &dyn GitBackend) -> anyhow::Result<String> {
+    get_config(backend, "user.email")
+}
+
+pub fn get_ssh_command(backend: &dyn GitBackend) -> anyhow::Result<String> {
+    get_config(backend, "core.sshCommand")
+}
+
+pub fn get_config(backend: &dyn GitBackend, key: &str) -> anyhow::Result<String> {
+    backend.get_config(key)
+}
+
+pub fn get_bool_config(backend: &dyn GitBackend, key: &str) -> anyhow::Result<bool> {
+    backend.get_bool_config(key)
+}
+
+pub fn get_config_origin(
+    backend: &dyn GitBackend,
+    key: &str,
+) -> anyhow::Result<Option<ConfigOrigin>> {
+    backend.get_config_origin(key)
+}
+
+/// Where a value was set, for explaining why Git is using it, e.g. ` (global config ~/.gitconfig)`
+pub fn describe_config_origin(backend: &dyn GitBackend, key: &str) -> anyhow::Result<String> {
+    Ok(get_config_origin(backend, key)?
+        .map(|origin| format!(" ({})", origin))
+        .unwrap_or_default())
 }
@@ -1,40 +1,120 @@
-use crate::git::common::{get_credentials_helper, get_origin_url};
+use crate::config::{Config, LazyConfig};
+use crate::git::backend::{get_backend, GitBackend};
+use crate::git::common::get_credentials_helper;
+use crate::git::resolve::{resolve_identity, resolve_identity_and_url};
+use crate::git::GIT_SERVICE;
 use crate::identity::Identity;
 use anyhow::{anyhow, Context};
-use std::io::Write;
-use std::process::{ChildStdin, Command, Stdio};
+use std::io::{stdin, Write};
+use std::process::{Command, Stdio};
 use url::Url;
 
-pub fn get_current_credential(identity: &Identity) -> anyhow::Result<String> {
-    if get_credentials_helper()?.is_empty() {
+/// The token the credentials helper gives Git for the identity's account at `url`
+pub fn get_current_credential(
+    backend: &dyn GitBackend,
+    identity: &Identity,
+    url: &str,
+) -> anyhow::Result<String> {
+    if get_credentials_helper(backend)?.is_empty() {
         return Err(anyhow!("No credentials helper configured"));
     }
 
-    let credentials_command = Command::new("git")
-        .args(["credential", "fill"])
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .with_context(|| "Failed to start Git credentials helper")?;
-    let mut credentials_command_stdin = credentials_command.stdin.as_ref().unwrap();
+    let description = credential_description(identity, url)?;
+    let output = run_git_credential_command("fill", description.as_str())?;
+    for line in output.split('\n') {
+        let parts: Vec<&str> = line.split('=').collect();
+        if Some("password") == parts.first().cloned() {
+            return Ok(parts.last().unwrap().to_string());
+        }
+    }
+
+    Err(anyhow!("No password found"))
+}
+
+pub fn run_git_sync_credentials(config: &mut LazyConfig) -> anyhow::Result<()> {
+    config.required()?;
+
+    let backend = get_backend();
+    let (identity, url) = resolve_identity_and_url(config, backend.as_ref())?;
+    if identity.token().is_none() {
+        return Err(anyhow!("Identity `{}` has no token to sync", identity.id()));
+    }
+
+    if !uses_credentials_helper(url.as_str()) {
+        return Err(anyhow!(
+            "The remote `{}` doesn't use HTTP, Git won't ask the credentials helper for a token",
+            url
+        ));
+    }
+
+    if !sync_credentials(backend.as_ref(), &identity, url.as_str())? {
+        return Err(anyhow!("No credentials helper configured"));
+    }
+
+    println!(
+        "Stored the token for `{}` in your Git credentials helper",
+        identity.id()
+    );
 
-    let user = identity.user();
-    if user.is_none() {
-        return Err(anyhow!("Missing username for identity {}", identity));
+    Ok(())
+}
+
+/// Replace whatever the credentials helper holds for the identity's account at `url` with its configured token.
+/// Returns false when there's no token, no helper or the URL doesn't use the helper, so nothing was done.
+pub fn sync_credentials(
+    backend: &dyn GitBackend,
+    identity: &Identity,
+    url: &str,
+) -> anyhow::Result<bool> {
+    // SSH remotes authenticate with keys, the credentials helper is never asked
+    if !uses_credentials_helper(url) {
+        return Ok(false);
+    }
+
+    let token = match identity.token() {
+        Some(token) => token,
+        None => return Ok(false),
+    };
+
+    if get_credentials_helper(backend)?.is_empty() {
+        return Ok(false);
     }
-    credentials_command_stdin.write_fmt(format_args!("username={}\n", user.unwrap()))?;
 
-    let origin_url = get_origin_url()?;
-    if !origin_url.is_empty() {
-        credentials_command_stdin.write_fmt(format_args!("url={}\n", origin_url))?;
+    let description = credential_description(identity, url)?;
+
+    // Rejecting first clears a stale token, helpers don't all overwrite on approve
+    run_git_credential_command("reject", description.as_str())?;
+    run_git_credential_command(
+        "approve",
+        format!("{}password={}\n", description, token).as_str(),
+    )?;
+
+    Ok(true)
+}
+
+/// Git only asks the credentials helper about HTTP remotes. Without a URL the identity's `match_url` is used instead.
+fn uses_credentials_helper(url: &str) -> bool {
+    url.is_empty() || url.starts_with("http://") || url.starts_with("https://")
+}
+
+/// The username, protocol and host which identify the account to the credentials helper
+fn credential_description(identity: &Identity, url: &str) -> anyhow::Result<String> {
+    let user = identity
+        .user()
+        .ok_or_else(|| anyhow!("Missing username for identity {}", identity))?;
+    let mut description = String::new();
+
+    if !url.is_empty() {
+        // Git resets every other attribute when it reads `url`, so it has to come first
+        description.push_str(format!("url={}\n", url).as_str());
 
-        write_url_info(&mut credentials_command_stdin, origin_url.as_str())?;
+        write_url_info(&mut description, url)?;
     } else if let Some(mut match_url) = identity.match_url().cloned() {
         if match_url.ends_with('*') {
             match_url.pop();
         }
 
-        write_url_info(&mut credentials_command_stdin, match_url.as_str())?;
+        write_url_info(&mut description, match_url.as_str())?;
     } else {
         return Err(anyhow!(
             "Not in a Git repository and no Match url configured for identity - {}",
@@ -42,29 +122,16 @@ pub fn get_current_credential(identity: &Identity) -> anyhow::Result<String> {
         ));
     };
 
-    // Final newline as end-of-input to the credentials helper
-    credentials_command_stdin.write_all(&[b'\n'])?;
-
-    let output = String::from_utf8(credentials_command.wait_with_output()?.stdout)?;
-    for line in output.split('\n') {
-        let parts: Vec<&str> = line.split('=').collect();
-        if Some("password") == parts.first().cloned() {
-            return Ok(parts.last().unwrap().to_string());
-        }
-    }
+    description.push_str(format!("username={}\n", user).as_str());
 
-    Err(anyhow!("No password found"))
+    Ok(description)
 }
 
-fn write_url_info(
-    credentials_command_stdin: &mut &ChildStdin,
-    input_url: &str,
-) -> anyhow::Result<()> {
+fn write_url_info(description: &mut String, input_url: &str) -> anyhow::Result<()> {
     let input_url_parsed = Url::parse(input_url)?;
-    credentials_command_stdin
-        .write_fmt(format_args!("protocol={}\n", input_url_parsed.scheme()))?;
+    description.push_str(format!("protocol={}\n", input_url_parsed.scheme()).as_str());
     if let Some(h) = input_url_parsed.host_str() {
-        credentials_command_stdin.write_fmt(format_args!("host={}\n", h))?;
+        description.push_str(format!("host={}\n", h).as_str());
     } else {
         return Err(anyhow!(
             "Configured url does not have a host - {}",
@@ -74,3 +141,152 @@ fn write_url_info(
 
     Ok(())
 }
+
+fn run_git_credential_command(operation: &str, input: &str) -> anyhow::Result<String> {
+    let mut credentials_command = Command::new("git")
+        .args(["credential", operation])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| "Failed to start Git credentials helper")?;
+
+    let mut credentials_command_stdin = credentials_command.stdin.take().unwrap();
+    credentials_command_stdin.write_all(input.as_bytes())?;
+    // Final newline as end-of-input to the credentials helper
+    credentials_command_stdin.write_all(b"\n")?;
+    drop(credentials_command_stdin);
+
+    let output = credentials_command.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow!("Git credentials helper failed to {}", operation));
+    }
+
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// A request from Git using the credential helper protocol, see `git help credential`
+#[derive(Default)]
+struct CredentialRequest {
+    protocol: Option<String>,
+    host: Option<String>,
+    path: Option<String>,
+    username: Option<String>,
+}
+
+pub fn run_git_credential(config: &mut LazyConfig, operation: &str) -> anyhow::Result<()> {
+    let request = read_credential_request()?;
+
+    // Tokens are only read from identity.toml, which is edited by hand, so there's nothing to store or erase
+    if operation != "get" {
+        return Ok(());
+    }
+
+    config.required()?;
+
+    if let Some(identity) = find_credential_identity(config, &request)? {
+        if let (Some(user), Some(token)) = (identity.user(), identity.token()) {
+            println!("username={}", user);
+            println!("password={}", token);
+        }
+    }
+
+    Ok(())
+}
+
+fn read_credential_request() -> anyhow::Result<CredentialRequest> {
+    let mut request = CredentialRequest::default();
+
+    for line in stdin().lines() {
+        let line = line?;
+        if line.is_empty() {
+            break;
+        }
+
+        let (key, value) = match line.split_once('=') {
+            Some(kv) => kv,
+            None => continue,
+        };
+        match key {
+            "url" => {
+                let url = Url::parse(value)
+                    .with_context(|| format!("Invalid URL from Git - {}", value))?;
+                request.protocol = Some(url.scheme().to_string());
+                request.host = url.host_str().map(|h| h.to_string());
+                request.path =
+                    Some(url.path().trim_start_matches('/').to_string()).filter(|p| !p.is_empty());
+                if !url.username().is_empty() {
+                    request.username = Some(url.username().to_string());
+                }
+            }
+            "protocol" => request.protocol = Some(value.to_string()),
+            "host" => request.host = Some(value.to_string()),
+            "path" => request.path = Some(value.to_string()),
+            "username" => request.username = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    Ok(request)
+}
+
+fn find_credential_identity<'a>(
+    config: &'a Config,
+    request: &CredentialRequest,
+) -> anyhow::Result<Option<Identity<'a>>> {
+    let (protocol, host) = match (&request.protocol, &request.host) {
+        (Some(protocol), Some(host)) => (protocol, host),
+        _ => return Ok(None),
+    };
+
+    let matches_user = |identity: &Identity| {
+        request.username.is_none() || identity.user() == request.username.as_ref()
+    };
+
+    let candidates: Vec<Identity> = match &request.path {
+        Some(path) => {
+            let url = format!("{}://{}/{}", protocol, host, path);
+            config
+                .identities_for_url(GIT_SERVICE, url.as_str())
+                .into_iter()
+                .filter(matches_user)
+                .collect()
+        }
+        // Git leaves out the path unless `credential.useHttpPath` is set, so all we know is the host
+        None => config
+            .identities_for_service(GIT_SERVICE)
+            .into_iter()
+            .filter(|identity| {
+                match_url_host(identity).as_ref() == Some(&(protocol.clone(), host.clone()))
+            })
+            .filter(matches_user)
+            .collect(),
+    };
+
+    if candidates.len() <= 1 {
+        return Ok(candidates.into_iter().next());
+    }
+
+    // Git runs the helper in the repository, so its origin can settle which account on a shared host to use
+    if let Ok(identity) = resolve_identity(config, get_backend().as_ref()) {
+        if candidates
+            .iter()
+            .any(|c| c.id() == identity.id() && c.user() == identity.user())
+        {
+            return Ok(Some(identity));
+        }
+    }
+
+    eprintln!(
+        "identity: several accounts match {}://{}, set `git config --global credential.useHttpPath true` to tell them apart",
+        protocol, host
+    );
+    Ok(None)
+}
+
+/// The protocol and host of the account's `match_url`, when it is a URL rather than an SSH address
+fn match_url_host(identity: &Identity) -> Option<(String, String)> {
+    let match_url = identity.match_url()?;
+    let url = Url::parse(match_url.trim_end_matches('*')).ok()?;
+
+    Some((url.scheme().to_string(), url.host_str()?.to_string()))
+}
@@ -0,0 +1,258 @@
+use crate::git::backend::{get_backend, GitBackend};
+use crate::git::common::get_toplevel;
+use crate::git::install::PRE_COMMIT;
+use anyhow::{anyhow, Context};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Hook managers which generate `.git/hooks/pre-commit` from their own config, so the check has to be registered there.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Framework {
+    PreCommit,
+    Lefthook,
+}
+
+pub const FRAMEWORKS: [Framework; 2] = [Framework::PreCommit, Framework::Lefthook];
+
+impl Framework {
+    pub fn from_name(name: &str) -> anyhow::Result<Framework> {
+        FRAMEWORKS
+            .iter()
+            .find(|f| f.name() == name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unknown hook framework {}", name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Framework::PreCommit => "pre-commit",
+            Framework::Lefthook => "lefthook",
+        }
+    }
+
+    /// Text which is only found in the hooks the framework generates
+    fn hook_marker(&self) -> &'static str {
+        match self {
+            Framework::PreCommit => "# File generated by pre-commit: https://pre-commit.com",
+            Framework::Lefthook => "call_lefthook",
+        }
+    }
+
+    /// The framework which generated a hook, if any
+    pub fn from_hook(content: &str) -> Option<Framework> {
+        FRAMEWORKS
+            .iter()
+            .find(|f| content.contains(f.hook_marker()))
+            .cloned()
+    }
+
+    /// Whether the framework's config runs the identity check
+    pub fn is_registered(&self, backend: &dyn GitBackend) -> anyhow::Result<bool> {
+        // Bare repositories have nowhere to keep framework config
+        if get_toplevel(backend)?.is_empty() {
+            return Ok(false);
+        }
+
+        Ok(
+            read_framework_config(&framework_config_path(backend, *self)?)?
+                .contains(PRE_COMMIT.command),
+        )
+    }
+
+    /// The first file is created if none of them exist
+    fn config_files(&self) -> &'static [&'static str] {
+        match self {
+            Framework::PreCommit => &[".pre-commit-config.yaml"],
+            Framework::Lefthook => &[
+                "lefthook.yml",
+                ".lefthook.yml",
+                "lefthook.yaml",
+                ".lefthook.yaml",
+            ],
+        }
+    }
+
+    fn add_hook(&self, content: &str) -> String {
+        match self {
+            Framework::PreCommit => add_pre_commit_hook(content),
+            Framework::Lefthook => add_lefthook_hook(content),
+        }
+    }
+}
+
+pub fn run_git_install_framework(framework: Framework) -> anyhow::Result<()> {
+    let config_path = framework_config_path(get_backend().as_ref(), framework)?;
+
+    let content = read_framework_config(&config_path)?;
+    if content.contains(PRE_COMMIT.command) {
+        println!(
+            "Identity check already registered with {} in {:?}",
+            framework.name(),
+            config_path
+        );
+        return Ok(());
+    }
+
+    let content = framework.add_hook(content.as_str());
+    File::create(&config_path)
+        .with_context(|| format!("Failed to create {:?}", config_path))?
+        .write_all(content.as_bytes())
+        .with_context(|| format!("Failed to write {:?}", config_path))?;
+
+    println!(
+        "Identity check registered with {} in {:?}, run `{} install` if you haven't already",
+        framework.name(),
+        config_path,
+        framework.name()
+    );
+
+    Ok(())
+}
+
+/// The framework whose config contains the identity check, if any.
+pub fn get_registered_framework(backend: &dyn GitBackend) -> anyhow::Result<Option<Framework>> {
+    for framework in FRAMEWORKS {
+        if framework.is_registered(backend)? {
+            return Ok(Some(framework));
+        }
+    }
+
+    Ok(None)
+}
+
+fn framework_config_path(
+    backend: &dyn GitBackend,
+    framework: Framework,
+) -> anyhow::Result<PathBuf> {
+    let toplevel = get_toplevel(backend)?;
+    if toplevel.is_empty() {
+        return Err(anyhow!("Not in a Git repository"));
+    }
+    let toplevel = Path::new(toplevel.as_str());
+
+    let config_files = framework.config_files();
+    Ok(config_files
+        .iter()
+        .map(|f| toplevel.join(f))
+        .find(|p| p.exists())
+        .unwrap_or_else(|| toplevel.join(config_files[0])))
+}
+
+fn read_framework_config(config_path: &Path) -> anyhow::Result<String> {
+    let mut content = String::new();
+    if config_path.exists() {
+        File::open(config_path)
+            .with_context(|| format!("Failed to open {:?}", config_path))?
+            .read_to_string(&mut content)
+            .with_context(|| format!("Failed to read {:?}", config_path))?;
+    }
+
+    Ok(content)
+}
+
+fn add_pre_commit_hook(content: &str) -> String {
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+
+    let repos_line = lines
+        .iter()
+        .position(|l| l.trim_end() == "repos:" || l.trim_end() == "repos: []");
+
+    let index = match repos_line {
+        Some(i) => {
+            lines[i] = "repos:".to_string();
+            i + 1
+        }
+        None => {
+            lines.push("repos:".to_string());
+            lines.len()
+        }
+    };
+
+    // Match the indentation of the existing list, which may not be indented at all
+    let indent = lines[index..]
+        .iter()
+        .find(|l| l.trim_start().starts_with("- "))
+        .map(|l| indentation(l))
+        .unwrap_or(2);
+
+    let entry = [
+        "- repo: local",
+        "  hooks:",
+        "    - id: identity",
+        "      name: identity",
+        &format!("      entry: {}", PRE_COMMIT.command),
+        "      language: system",
+        "      pass_filenames: false",
+        "      always_run: true",
+    ];
+    for (offset, line) in entry.iter().enumerate() {
+        lines.insert(index + offset, format!("{}{}", " ".repeat(indent), line));
+    }
+
+    with_trailing_newline(lines.join("\n"))
+}
+
+fn add_lefthook_hook(content: &str) -> String {
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+
+    let run = format!("run: {}", PRE_COMMIT.command);
+
+    let pre_commit_line = match lines.iter().position(|l| l.trim_end() == "pre-commit:") {
+        Some(i) => i,
+        None => {
+            lines.extend([
+                "pre-commit:".to_string(),
+                "  commands:".to_string(),
+                "    identity:".to_string(),
+                format!("      {}", run),
+            ]);
+            return with_trailing_newline(lines.join("\n"));
+        }
+    };
+
+    let block_end = lines[pre_commit_line + 1..]
+        .iter()
+        .position(|l| !l.trim().is_empty() && indentation(l) == 0 && !l.starts_with('#'))
+        .map(|p| p + pre_commit_line + 1)
+        .unwrap_or(lines.len());
+
+    let child_indent = lines[pre_commit_line + 1..block_end]
+        .iter()
+        .find(|l| !l.trim().is_empty())
+        .map(|l| indentation(l))
+        .unwrap_or(2);
+
+    let commands_line = lines[pre_commit_line + 1..block_end]
+        .iter()
+        .position(|l| l.trim_end() == format!("{}commands:", " ".repeat(child_indent)))
+        .map(|p| p + pre_commit_line + 1);
+
+    let (index, indent, new_lines) = match commands_line {
+        Some(i) => (i + 1, child_indent * 2, vec![]),
+        None => (
+            pre_commit_line + 1,
+            child_indent * 2,
+            vec![format!("{}commands:", " ".repeat(child_indent))],
+        ),
+    };
+
+    let new_lines = new_lines.into_iter().chain([
+        format!("{}identity:", " ".repeat(indent)),
+        format!("{}{}", " ".repeat(indent + child_indent), run),
+    ]);
+    for (offset, line) in new_lines.enumerate() {
+        lines.insert(index + offset, line);
+    }
+
+    with_trailing_newline(lines.join("\n"))
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn with_trailing_newline(mut content: String) -> String {
+    content.push('\n');
+    content
+}
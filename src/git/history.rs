@@ -0,0 +1,205 @@
+use crate::config::LazyConfig;
+use crate::git::backend::{get_backend, GitBackend};
+use crate::git::commits::{identity_mismatches, list_commits, CommitIdentity};
+use crate::git::common::get_toplevel;
+use crate::git::resolve::resolve_identity;
+use crate::input::prompt_confirm;
+use anyhow::{anyhow, Context};
+use std::collections::HashSet;
+use std::fs::{read_to_string, write};
+use std::path::Path;
+use std::process::Command;
+
+/// Commits on the current branch which no remote has, the only ones which are safe to rewrite
+const UNPUSHED_REVISIONS: [&str; 3] = ["HEAD", "--not", "--remotes"];
+
+pub fn run_git_fix_history(
+    config: &mut LazyConfig,
+    dry_run: bool,
+    yes: bool,
+) -> anyhow::Result<()> {
+    config.required()?;
+
+    let backend = get_backend();
+    let identity = resolve_identity(config, backend.as_ref())?;
+    let (user, email) = match (identity.user(), identity.email()) {
+        (Some(user), Some(email)) => (user.clone(), email.clone()),
+        _ => {
+            return Err(anyhow!(
+                "Identity `{}` needs a username and email to fix history",
+                identity.id()
+            ))
+        }
+    };
+
+    // Only your own other identities are strays, anyone else is a genuine contributor
+    let stray_emails: HashSet<String> = config
+        .identity
+        .iter()
+        .filter_map(|ic| ic.email.clone())
+        .filter(|e| *e != email)
+        .collect();
+
+    let unpushed = list_commits(&UNPUSHED_REVISIONS)?;
+    let unpushed_shas: HashSet<&String> = unpushed.iter().map(|c| &c.sha).collect();
+    let pushed: Vec<CommitIdentity> = list_commits(&["HEAD"])?
+        .into_iter()
+        .filter(|c| !unpushed_shas.contains(&c.sha))
+        .collect();
+
+    // Pushed commits can't be rewritten without breaking everyone else's clones, so map them in .mailmap instead
+    let mut mailmap_entries = vec![];
+    let mut skipped = HashSet::new();
+    for commit in &pushed {
+        for role in identity_mismatches(&identity, commit) {
+            let (name, commit_email) = match role {
+                "author" => (&commit.author_name, &commit.author_email),
+                _ => (&commit.committer_name, &commit.committer_email),
+            };
+
+            if stray_emails.contains(commit_email) || *commit_email == email {
+                let entry = format!("{} <{}> {} <{}>", user, email, name, commit_email);
+                if !mailmap_entries.contains(&entry) {
+                    mailmap_entries.push(entry);
+                }
+            } else {
+                skipped.insert(commit_email.clone());
+            }
+        }
+    }
+
+    let strays: Vec<&CommitIdentity> = unpushed
+        .iter()
+        .filter(|c| {
+            stray_emails.contains(&c.author_email) || stray_emails.contains(&c.committer_email)
+        })
+        .collect();
+
+    if mailmap_entries.is_empty() {
+        println!("No pushed commits need mapping in .mailmap");
+    } else {
+        println!("Pushed commits to map in .mailmap:");
+        for entry in &mailmap_entries {
+            println!("  {}", entry);
+        }
+    }
+    if !skipped.is_empty() {
+        println!(
+            "Leaving {} email(s) which aren't one of your identities alone",
+            skipped.len()
+        );
+    }
+
+    if strays.is_empty() {
+        println!("No unpushed commits need rewriting");
+    } else {
+        println!(
+            "Unpushed commits to rewrite as `{}` - {} <{}>:",
+            identity.id(),
+            user,
+            email
+        );
+        for commit in &strays {
+            println!("  {} {}", &commit.sha[..7], commit.summary);
+        }
+    }
+
+    if mailmap_entries.is_empty() && strays.is_empty() {
+        return Ok(());
+    }
+
+    if dry_run {
+        println!("Nothing was changed, run without --dry-run to make these changes");
+        return Ok(());
+    }
+
+    if !yes && !prompt_confirm("Rewrite history and update .mailmap? (y/n)")? {
+        println!("Nothing was changed");
+        return Ok(());
+    }
+
+    if !mailmap_entries.is_empty() {
+        let added = update_mailmap(backend.as_ref(), &mailmap_entries)?;
+        println!("Added {} entries to .mailmap for pushed commits", added);
+    }
+
+    if !strays.is_empty() {
+        rewrite_commits(&user, &email, &stray_emails)?;
+        println!(
+            "Rewrote {} unpushed commit(s) as `{}` - {} <{}>",
+            strays.len(),
+            identity.id(),
+            user,
+            email
+        );
+    }
+
+    Ok(())
+}
+
+/// Append the entries which aren't already in the repository's `.mailmap`, returning how many were added
+fn update_mailmap(backend: &dyn GitBackend, entries: &[String]) -> anyhow::Result<usize> {
+    let mailmap_path = Path::new(&get_toplevel(backend)?).join(".mailmap");
+
+    let mut content = if mailmap_path.exists() {
+        read_to_string(&mailmap_path)
+            .with_context(|| format!("Failed to read mailmap - {:?}", mailmap_path))?
+    } else {
+        String::new()
+    };
+
+    let existing: HashSet<String> = content.lines().map(|l| l.trim().to_string()).collect();
+    let new_entries: Vec<&String> = entries.iter().filter(|e| !existing.contains(*e)).collect();
+
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    for entry in &new_entries {
+        content.push_str(entry);
+        content.push('\n');
+    }
+
+    write(&mailmap_path, content)
+        .with_context(|| format!("Failed to write mailmap - {:?}", mailmap_path))?;
+
+    Ok(new_entries.len())
+}
+
+/// Replaces the author or committer of a commit being rewritten, only when it's one of the stray emails
+const STRAY_ENV_FILTER: &str = r#"
+if printf '%s\n' "$IDENTITY_STRAY_EMAILS" | grep -qxF -e "$GIT_AUTHOR_EMAIL"; then
+    GIT_AUTHOR_NAME="$IDENTITY_NAME"
+    GIT_AUTHOR_EMAIL="$IDENTITY_EMAIL"
+fi
+if printf '%s\n' "$IDENTITY_STRAY_EMAILS" | grep -qxF -e "$GIT_COMMITTER_EMAIL"; then
+    GIT_COMMITTER_NAME="$IDENTITY_NAME"
+    GIT_COMMITTER_EMAIL="$IDENTITY_EMAIL"
+fi
+"#;
+
+/// Rewrite the unpushed commits, keeping the author and committer of each commit unless they're a stray email.
+fn rewrite_commits(user: &str, email: &str, stray_emails: &HashSet<String>) -> anyhow::Result<()> {
+    // The same commits that were checked, a range from the oldest stray would also take in pushed commits that were
+    // merged in. Unlike a rebase, this leaves the committer of everyone else's commits alone too.
+    let output = Command::new("git")
+        .args(["filter-branch", "--force", "--env-filter", STRAY_ENV_FILTER])
+        .arg("--")
+        .args(UNPUSHED_REVISIONS)
+        .env("FILTER_BRANCH_SQUELCH_WARNING", "1")
+        .env("IDENTITY_NAME", user)
+        .env("IDENTITY_EMAIL", email)
+        .env(
+            "IDENTITY_STRAY_EMAILS",
+            stray_emails.iter().cloned().collect::<Vec<_>>().join("\n"),
+        )
+        .output()?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to rewrite commits - {}",
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        ));
+    }
+
+    Ok(())
+}
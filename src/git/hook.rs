@@ -1,35 +1,305 @@
-use crate::config::LazyConfig;
-use crate::git::common::{get_email, get_origin_url, get_username};
-use crate::git::GIT_SERVICE;
+use crate::config::{HookMode, LazyConfig};
+use crate::git::backend::{get_backend, GitBackend};
+use crate::git::commits::{commit_exists, identity_mismatches, is_zero_sha, list_commits};
+use crate::git::common::{describe_config_origin, get_email, get_toplevel, get_username};
+use crate::git::resolve::{resolve_identity, resolve_identity_for_url};
+use crate::git::signing::signing_mismatches;
+use crate::git::switch::{apply_switch, ConfigScope};
+use crate::identity::Identity;
+use anyhow::{anyhow, Context};
+use std::collections::HashSet;
+use std::fs::{read_to_string, write};
+use std::io::stdin;
 use std::process::exit;
 
-pub fn run_git_pre_commit_hook(config: &mut LazyConfig) -> anyhow::Result<()> {
+pub fn run_git_pre_commit_hook(
+    config: &mut LazyConfig,
+    mode: Option<HookMode>,
+) -> anyhow::Result<()> {
     config.required()?;
 
-    let username = get_username()?;
+    let mode = config.hook_mode(mode);
 
-    let email = get_email()?;
+    let backend = get_backend();
+    let identity = resolve_identity(config, backend.as_ref())?;
+    if identity.is_default() {
+        eprintln!(
+            "No identity matched this repository, using default identity `{}`",
+            identity.id()
+        );
+    }
+
+    let mismatches = get_config_mismatches(backend.as_ref(), &identity)?;
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+
+    for mismatch in mismatches {
+        eprintln!("{}", mismatch);
+    }
+
+    match mode {
+        HookMode::Fail => exit(1),
+        HookMode::Warn => Ok(()),
+        HookMode::Fix => {
+            // Git has already read the author for this commit, so the switch only helps the next attempt
+            apply_switch(backend.as_ref(), &identity, ConfigScope::Local)?;
+            eprintln!(
+                "Switched this repository to identity `{}`, run the commit again to use it",
+                identity.id()
+            );
+            exit(1);
+        }
+    }
+}
 
-    let origin = get_origin_url()?;
+/// How the repository's Git config differs from the identity, empty when it's set up correctly
+pub fn get_config_mismatches(
+    backend: &dyn GitBackend,
+    identity: &Identity,
+) -> anyhow::Result<Vec<String>> {
+    let username = get_username(backend)?;
+
+    let email = get_email(backend)?;
+
+    let mut mismatches = vec![];
 
-    let identity = config.account_for_url(GIT_SERVICE, origin.as_str())?;
     if identity.user() != Some(&username) {
-        eprintln!(
-            "Username mismatch - expected={} != actual={}",
+        mismatches.push(format!(
+            "Username mismatch - expected={} != actual={}{}",
             identity.user().unwrap_or(&"no username".to_string()),
-            username
-        );
-        exit(1);
+            username,
+            describe_config_origin(backend, "user.name")?
+        ));
     }
 
     if identity.email() != Some(&email) {
-        eprintln!(
-            "Email mismatch - expected={} != actual={}",
+        mismatches.push(format!(
+            "Email mismatch - expected={} != actual={}{}",
             identity.email().unwrap_or(&"missing".to_string()),
-            email
+            email,
+            describe_config_origin(backend, "user.email")?
+        ));
+    }
+
+    mismatches.extend(signing_mismatches(backend, identity)?);
+
+    Ok(mismatches)
+}
+
+pub fn run_git_chpwd_hook(config: &mut LazyConfig, apply: bool) -> anyhow::Result<()> {
+    let backend = get_backend();
+    let toplevel = get_toplevel(backend.as_ref())?;
+    if toplevel.is_empty() {
+        return Ok(());
+    }
+
+    // The shell remembers this so that it doesn't call back into us until it leaves the repository
+    println!("{}", toplevel);
+
+    config.required()?;
+
+    let identity = match resolve_identity(config, backend.as_ref()) {
+        Ok(identity) => identity,
+        Err(_) => return Ok(()),
+    };
+
+    if identity.user() == Some(&get_username(backend.as_ref())?)
+        && identity.email() == Some(&get_email(backend.as_ref())?)
+    {
+        return Ok(());
+    }
+
+    if apply {
+        apply_switch(backend.as_ref(), &identity, ConfigScope::Local)?;
+        eprintln!(
+            "identity: switched to `{}` for this repository",
+            identity.id()
+        );
+    } else {
+        eprintln!(
+            "identity: this repository should use `{}`, run `identity switch --service git` to apply it",
+            identity.id()
+        );
+    }
+
+    Ok(())
+}
+
+pub fn run_git_pre_push_hook(
+    config: &mut LazyConfig,
+    mode: Option<HookMode>,
+    remote: Option<&String>,
+    url: Option<&String>,
+) -> anyhow::Result<()> {
+    config.required()?;
+
+    let mode = config.hook_mode(mode);
+
+    // Git tells us where the push is going, which may not be the origin
+    let backend = get_backend();
+    let identity = match url {
+        Some(url) => resolve_identity_for_url(config, backend.as_ref(), url.as_str())?,
+        None => resolve_identity(config, backend.as_ref())?,
+    };
+
+    let remotes_arg = remote.map(|r| format!("--remotes={}", r));
+
+    let mut seen = HashSet::new();
+    let mut mismatch_count = 0;
+    for line in stdin().lines() {
+        let line = line?;
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let (local_sha, remote_sha) = match parts[..] {
+            [_, local_sha, _, remote_sha] => (local_sha, remote_sha),
+            _ => continue,
+        };
+
+        // Deleting a remote ref doesn't push any commits
+        if is_zero_sha(local_sha) {
+            continue;
+        }
+
+        // Only the commits the remote doesn't already have
+        let mut revision_args = vec![local_sha, "--not"];
+        if !is_zero_sha(remote_sha) && commit_exists(remote_sha)? {
+            revision_args.push(remote_sha);
+        }
+        if let Some(remotes_arg) = &remotes_arg {
+            revision_args.push(remotes_arg.as_str());
+        }
+
+        for commit in list_commits(&revision_args)? {
+            if !seen.insert(commit.sha.clone()) {
+                continue;
+            }
+
+            for role in identity_mismatches(&identity, &commit) {
+                let (name, email) = match role {
+                    "author" => (&commit.author_name, &commit.author_email),
+                    _ => (&commit.committer_name, &commit.committer_email),
+                };
+                eprintln!(
+                    "Commit {} `{}` has {} `{} <{}>` but expected `{} <{}>`",
+                    &commit.sha[..commit.sha.len().min(10)],
+                    commit.summary,
+                    role,
+                    name,
+                    email,
+                    identity.user().unwrap_or(&"no username".to_string()),
+                    identity.email().unwrap_or(&"missing".to_string()),
+                );
+                mismatch_count += 1;
+            }
+        }
+    }
+
+    // Commits which are already made can't be fixed here, so fix rejects the push like fail does
+    if mismatch_count > 0 && mode != HookMode::Warn {
+        eprintln!(
+            "Push rejected, found {} mismatch(es) with identity `{}`",
+            mismatch_count,
+            identity.id()
         );
         exit(1);
     }
 
     Ok(())
 }
+
+const SIGNED_OFF_BY: &str = "signed-off-by:";
+
+pub fn run_git_commit_msg_hook(
+    config: &mut LazyConfig,
+    mode: Option<HookMode>,
+    message_path: Option<&String>,
+    rewrite: bool,
+) -> anyhow::Result<()> {
+    let message_path = message_path
+        .ok_or_else(|| anyhow!("Git didn't pass the commit message file to the hook"))?;
+
+    config.required()?;
+
+    let mode = config.hook_mode(mode);
+
+    let identity = resolve_identity(config, get_backend().as_ref())?;
+    let expected = match (identity.user(), identity.email()) {
+        (Some(user), Some(email)) => format!("{} <{}>", user, email),
+        _ => {
+            return Err(anyhow!(
+                "Identity `{}` needs a username and email to check Signed-off-by trailers",
+                identity.id()
+            ))
+        }
+    };
+
+    // Signing off as someone else is normal when passing on their patch, it's only wrong when it's another of your identities
+    let other_emails: HashSet<&str> = config
+        .identity
+        .iter()
+        .filter(|ic| ic.id != identity.id())
+        .filter_map(|ic| ic.email.as_deref())
+        .filter(|email| Some(*email) != identity.email().map(|e| e.as_str()))
+        .collect();
+
+    let message = read_to_string(message_path)
+        .with_context(|| format!("Failed to read commit message - {:?}", message_path))?;
+
+    let mut mismatches = vec![];
+    let lines: Vec<String> = message
+        .lines()
+        .map(|line| match signed_off_by_email(line) {
+            Some(email) if other_emails.contains(email) => {
+                mismatches.push(line.to_string());
+                format!("Signed-off-by: {}", expected)
+            }
+            _ => line.to_string(),
+        })
+        .collect();
+
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+
+    // The message is still open to change at this point, so it can be fixed for this commit
+    if rewrite || mode == HookMode::Fix {
+        let mut content = lines.join("\n");
+        content.push('\n');
+        write(message_path, content)
+            .with_context(|| format!("Failed to write commit message - {:?}", message_path))?;
+
+        for mismatch in mismatches {
+            eprintln!("Rewrote `{}` to `Signed-off-by: {}`", mismatch, expected);
+        }
+        return Ok(());
+    }
+
+    for mismatch in mismatches {
+        eprintln!(
+            "Signed-off-by mismatch - expected={} != actual={}",
+            expected,
+            mismatch[SIGNED_OFF_BY.len()..].trim()
+        );
+    }
+
+    match mode {
+        HookMode::Warn => Ok(()),
+        _ => exit(1),
+    }
+}
+
+/// The email of a `Signed-off-by` trailer, Git treats trailer names case insensitively
+fn signed_off_by_email(line: &str) -> Option<&str> {
+    if !line
+        .get(..SIGNED_OFF_BY.len())?
+        .eq_ignore_ascii_case(SIGNED_OFF_BY)
+    {
+        return None;
+    }
+
+    let value = &line[SIGNED_OFF_BY.len()..];
+    let start = value.find('<')?;
+    let end = value.rfind('>')?;
+
+    (start < end).then(|| value[start + 1..end].trim())
+}
@@ -0,0 +1,847 @@
+use crate::config::url_matches_pattern;
+use crate::git::backend::{ConfigOrigin, GitBackend};
+use anyhow::anyhow;
+use glob::{MatchOptions, Pattern};
+use std::env;
+use std::fs::{canonicalize, read_to_string};
+use std::path::{Path, PathBuf};
+
+/// These change where Git looks for the repository or its config. They're rare enough that `git` can deal with them.
+const UNSUPPORTED_ENVIRONMENT: [&str; 6] = [
+    "GIT_WORK_TREE",
+    "GIT_COMMON_DIR",
+    "GIT_CONFIG",
+    "GIT_CONFIG_COUNT",
+    "GIT_CEILING_DIRECTORIES",
+    "GIT_DISCOVERY_ACROSS_FILESYSTEM",
+];
+
+/// Git refuses to go deeper than this, so a config which does is broken or circular
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+/// Where Git is usually built to look for the system config
+const DEFAULT_SYSTEM_CONFIG: &str = "/etc/gitconfig";
+
+struct ConfigEntry {
+    /// `section.subsection.name` with the section and name lower cased, as Git compares them
+    key: String,
+    /// `None` for a key written without `=`, which Git treats as true
+    value: Option<String>,
+    /// The layer the value came from, named the way `git config --show-scope` names it
+    scope: &'static str,
+    /// The file which set the value, which may have been included by one of the layers
+    origin: PathBuf,
+}
+
+struct Repository {
+    toplevel: PathBuf,
+    git_dir: PathBuf,
+    common_dir: PathBuf,
+    branch: String,
+}
+
+/// Reads the repository and config files directly rather than asking `git`
+pub struct InProcessBackend {
+    repository: Option<Repository>,
+    entries: Vec<ConfigEntry>,
+    global_entries: Vec<ConfigEntry>,
+    files: Vec<PathBuf>,
+}
+
+impl InProcessBackend {
+    /// Find the repository and load its config, or `None` when something needs `git` to get it right.
+    pub fn discover() -> anyhow::Result<Option<InProcessBackend>> {
+        if UNSUPPORTED_ENVIRONMENT
+            .iter()
+            .any(|name| env::var_os(name).is_some())
+        {
+            return Ok(None);
+        }
+
+        // Git sets `GIT_DIR` for hooks in linked worktrees, which it runs from the top of the working tree
+        let current_dir = env::current_dir()?;
+        let discovery = match env::var_os("GIT_DIR") {
+            Some(git_dir) => open_repository(&current_dir, current_dir.join(git_dir))?,
+            None => find_repository(&current_dir)?,
+        };
+        let repository = match discovery {
+            Discovery::Found(repository) => Some(repository),
+            Discovery::NotFound => None,
+            Discovery::Unsupported => return Ok(None),
+        };
+
+        let global_configs = global_config_paths();
+
+        // `hasconfig:remote.*.url:` depends on every remote URL, so they're collected first without those includes
+        let mut loader = ConfigLoader::new(repository.as_ref(), None);
+        if !loader.load_layers(&global_configs)? {
+            return Ok(None);
+        }
+
+        if loader.has_remote_url_conditions {
+            let remote_urls = collect_remote_urls(&loader.entries);
+            loader = ConfigLoader::new(repository.as_ref(), Some(remote_urls.clone()));
+            if !loader.load_layers(&global_configs)? {
+                return Ok(None);
+            }
+
+            // Git refuses to let a `hasconfig` include add remotes, since that could change which includes apply
+            if collect_remote_urls(&loader.entries) != remote_urls {
+                return Ok(None);
+            }
+        }
+
+        // The working tree is somewhere else, or there isn't one. Submodules set `core.worktree` pointing back at
+        // where they're checked out, which is the same place we found.
+        let worktree_elsewhere = match (lookup(&loader.entries, "core.worktree"), &repository) {
+            (None, _) => false,
+            (Some(Some(worktree)), Some(repository)) => {
+                canonicalize(repository.git_dir.join(worktree))
+                    .ok()
+                    .as_ref()
+                    != Some(&repository.toplevel)
+            }
+            (Some(_), _) => true,
+        };
+        if worktree_elsewhere || lookup(&loader.entries, "core.bare").map_or(false, is_true) {
+            return Ok(None);
+        }
+        let entries = loader.entries;
+        let files = loader.files;
+
+        // `git config --global` reads just the global files, without following includes
+        let mut global_loader = ConfigLoader::new(repository.as_ref(), None);
+        global_loader.scope = "global";
+        for global_config in &global_configs {
+            if !global_loader.load(global_config, false, 0)? {
+                return Ok(None);
+            }
+        }
+
+        let global_entries = global_loader.entries;
+
+        Ok(Some(InProcessBackend {
+            repository,
+            entries,
+            global_entries,
+            files,
+        }))
+    }
+}
+
+impl GitBackend for InProcessBackend {
+    fn get_toplevel(&self) -> anyhow::Result<String> {
+        Ok(self
+            .repository
+            .as_ref()
+            .map(|r| r.toplevel.display().to_string())
+            .unwrap_or_default())
+    }
+
+    fn get_config(&self, key: &str) -> anyhow::Result<String> {
+        Ok(lookup(&self.entries, normalize_key(key).as_str())
+            .flatten()
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn get_bool_config(&self, key: &str) -> anyhow::Result<bool> {
+        Ok(lookup(&self.entries, normalize_key(key).as_str()).map_or(false, is_true))
+    }
+
+    fn get_config_origin(&self, key: &str) -> anyhow::Result<Option<ConfigOrigin>> {
+        let key = normalize_key(key);
+
+        Ok(self
+            .entries
+            .iter()
+            .rev()
+            .find(|e| e.key == key)
+            .map(|e| ConfigOrigin {
+                scope: e.scope.to_string(),
+                path: e.origin.display().to_string(),
+            }))
+    }
+
+    fn get_global_config(&self, key: &str) -> anyhow::Result<String> {
+        Ok(lookup(&self.global_entries, normalize_key(key).as_str())
+            .flatten()
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn get_config_files(&self) -> anyhow::Result<Vec<String>> {
+        Ok(self.files.iter().map(|f| f.display().to_string()).collect())
+    }
+
+    fn get_remotes(&self) -> anyhow::Result<Vec<String>> {
+        if self.repository.is_none() {
+            return Ok(vec![]);
+        }
+
+        let mut remotes: Vec<String> = vec![];
+        for entry in &self.entries {
+            if let Some(("remote", remote, _)) = split_key(entry.key.as_str()) {
+                if !remotes.iter().any(|r| r == remote) {
+                    remotes.push(remote.to_string());
+                }
+            }
+        }
+
+        Ok(remotes)
+    }
+
+    fn get_remote_url(&self, remote: &str) -> anyhow::Result<String> {
+        if self.repository.is_none() {
+            return Ok(String::new());
+        }
+
+        // Git uses the first URL when a remote has several
+        let key = format!("remote.{}.url", remote);
+        let url = match self
+            .entries
+            .iter()
+            .find(|e| e.key == key)
+            .and_then(|e| e.value.as_ref())
+        {
+            Some(url) => url,
+            None => return Ok(String::new()),
+        };
+
+        // The longest matching `insteadOf` prefix wins
+        let rewrite = self
+            .entries
+            .iter()
+            .filter_map(|e| match (split_key(e.key.as_str()), &e.value) {
+                (Some(("url", base, "insteadof")), Some(prefix))
+                    if url.starts_with(prefix.as_str()) =>
+                {
+                    Some((base, prefix))
+                }
+                _ => None,
+            })
+            .max_by_key(|(_, prefix)| prefix.len());
+
+        Ok(match rewrite {
+            Some((base, prefix)) => format!("{}{}", base, &url[prefix.len()..]),
+            None => url.clone(),
+        })
+    }
+
+    fn get_current_branch(&self) -> anyhow::Result<String> {
+        Ok(self
+            .repository
+            .as_ref()
+            .map(|r| r.branch.clone())
+            .unwrap_or_default())
+    }
+}
+
+enum Discovery {
+    Found(Repository),
+    NotFound,
+    /// Something `git` would handle in a way that isn't worth copying, like a bare repository
+    Unsupported,
+}
+
+/// Walk up from `start` the way Git does, looking for a `.git` directory or a `.git` file pointing at one.
+fn find_repository(start: &Path) -> anyhow::Result<Discovery> {
+    for dir in start.ancestors() {
+        let dot_git = dir.join(".git");
+
+        let git_dir = if dot_git.is_dir() {
+            dot_git
+        } else if dot_git.is_file() {
+            // Linked worktrees and submodules have a file with `gitdir: <path>`
+            let content = read_to_string(&dot_git)?;
+            match content.trim_end().strip_prefix("gitdir: ") {
+                Some(path) => dir.join(path),
+                None => return Ok(Discovery::Unsupported),
+            }
+        } else if dir.join("HEAD").is_file() && dir.join("objects").is_dir() {
+            // Inside a bare repository, or the `.git` directory itself
+            return Ok(Discovery::Unsupported);
+        } else {
+            continue;
+        };
+
+        return open_repository(dir, git_dir);
+    }
+
+    Ok(Discovery::NotFound)
+}
+
+/// The repository checked out at `toplevel`, with its config and HEAD in `git_dir`
+fn open_repository(toplevel: &Path, git_dir: PathBuf) -> anyhow::Result<Discovery> {
+    let git_dir = canonicalize(&git_dir).unwrap_or(git_dir);
+    let head = match read_to_string(git_dir.join("HEAD")) {
+        Ok(head) => head,
+        Err(_) => return Ok(Discovery::Unsupported),
+    };
+
+    // A linked worktree's own directory points at the main one, which has the shared config
+    let common_dir = match read_to_string(git_dir.join("commondir")) {
+        Ok(common_dir) => {
+            let common_dir = git_dir.join(common_dir.trim_end());
+            canonicalize(&common_dir).unwrap_or(common_dir)
+        }
+        Err(_) => git_dir.clone(),
+    };
+
+    Ok(Discovery::Found(Repository {
+        toplevel: canonicalize(toplevel)?,
+        git_dir,
+        common_dir,
+        branch: head
+            .trim_end()
+            .strip_prefix("ref: refs/heads/")
+            .unwrap_or("")
+            .to_string(),
+    }))
+}
+
+/// Git's own location for the system config can be changed when it's built, this is where it usually is
+fn system_config_path() -> Option<PathBuf> {
+    if env::var("GIT_CONFIG_NOSYSTEM").map_or(false, |v| is_true(Some(&v))) {
+        return None;
+    }
+
+    Some(
+        env::var_os("GIT_CONFIG_SYSTEM")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_SYSTEM_CONFIG)),
+    )
+}
+
+fn global_config_paths() -> Vec<PathBuf> {
+    if let Some(global_config) = env::var_os("GIT_CONFIG_GLOBAL") {
+        return vec![PathBuf::from(global_config)];
+    }
+
+    let home_dir = match home::home_dir() {
+        Some(home_dir) => home_dir,
+        None => return vec![],
+    };
+
+    let xdg_config = match env::var_os("XDG_CONFIG_HOME").filter(|x| !x.is_empty()) {
+        Some(xdg_config_home) => PathBuf::from(xdg_config_home).join("git").join("config"),
+        None => home_dir.join(".config").join("git").join("config"),
+    };
+
+    vec![xdg_config, home_dir.join(".gitconfig")]
+}
+
+struct ConfigLoader<'a> {
+    repository: Option<&'a Repository>,
+    /// The scope of the layer being loaded, included files take the scope of the file including them
+    scope: &'static str,
+    entries: Vec<ConfigEntry>,
+    /// Every file that was looked for, including ones which don't exist yet
+    files: Vec<PathBuf>,
+    /// Every `remote.*.url`, for `hasconfig` includes. `None` while they're still being collected.
+    remote_urls: Option<Vec<String>>,
+    /// Whether a `hasconfig:remote.*.url:` include was seen
+    has_remote_url_conditions: bool,
+}
+
+impl<'a> ConfigLoader<'a> {
+    fn new(repository: Option<&'a Repository>, remote_urls: Option<Vec<String>>) -> Self {
+        ConfigLoader {
+            repository,
+            scope: "system",
+            entries: vec![],
+            files: vec![],
+            remote_urls,
+            has_remote_url_conditions: false,
+        }
+    }
+
+    /// Load the system, global, local, worktree and command line config in the order Git reads them
+    fn load_layers(&mut self, global_configs: &[PathBuf]) -> anyhow::Result<bool> {
+        self.scope = "system";
+        if let Some(system_config) = system_config_path() {
+            if !self.load(&system_config, true, 0)? {
+                return Ok(false);
+            }
+        }
+
+        self.scope = "global";
+        for global_config in global_configs {
+            if !self.load(global_config, true, 0)? {
+                return Ok(false);
+            }
+        }
+
+        if let Some(repository) = self.repository {
+            self.scope = "local";
+            if !self.load(&repository.common_dir.join("config"), true, 0)? {
+                return Ok(false);
+            }
+
+            self.scope = "worktree";
+            if lookup(&self.entries, "extensions.worktreeconfig").map_or(false, is_true)
+                && !self.load(&repository.git_dir.join("config.worktree"), true, 0)?
+            {
+                return Ok(false);
+            }
+        }
+
+        self.scope = "command";
+        Ok(self.load_parameters())
+    }
+
+    /// Add the `git -c` values, which Git passes on to the hooks it runs.
+    /// Returns false if they can't be read or include other files, which `git` can deal with.
+    fn load_parameters(&mut self) -> bool {
+        let parameters = match env::var("GIT_CONFIG_PARAMETERS") {
+            Ok(parameters) => parameters,
+            Err(_) => return true,
+        };
+
+        let entries = match parse_parameters(parameters.as_str()) {
+            Some(entries) => entries,
+            None => return false,
+        };
+
+        for (key, value) in entries {
+            if key == "include.path" || key.starts_with("includeif.") {
+                return false;
+            }
+
+            self.entries.push(ConfigEntry {
+                key,
+                value,
+                scope: self.scope,
+                // How `git config --show-origin` names it
+                origin: PathBuf::from("command line:"),
+            });
+        }
+
+        true
+    }
+
+    /// Add the entries from a config file, a missing file is the same as an empty one.
+    /// Returns false if the file uses something which needs `git` to read it.
+    fn load(&mut self, path: &Path, includes: bool, depth: usize) -> anyhow::Result<bool> {
+        if depth > MAX_INCLUDE_DEPTH {
+            return Ok(false);
+        }
+        self.files.push(path.to_path_buf());
+
+        let content = match read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => return Ok(true),
+        };
+
+        let entries = match parse_config(content.as_str()) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(false),
+        };
+
+        for (key, value) in entries {
+            let entry = ConfigEntry {
+                key,
+                value,
+                scope: self.scope,
+                origin: path.to_path_buf(),
+            };
+
+            let include_path = match (includes, split_key(entry.key.as_str()), &entry.value) {
+                (true, None, Some(include_path)) if entry.key == "include.path" => {
+                    Some(include_path.clone())
+                }
+                (true, Some(("includeif", condition, "path")), Some(include_path)) => {
+                    match self.include_condition_matches(condition, path) {
+                        Some(true) => Some(include_path.clone()),
+                        Some(false) => None,
+                        None => return Ok(false),
+                    }
+                }
+                _ => None,
+            };
+
+            self.entries.push(entry);
+
+            // Included entries go where the include is, so later entries still override them
+            if let Some(include_path) = include_path {
+                let include_path = resolve_include_path(include_path.as_str(), path);
+                if !self.load(&include_path, includes, depth + 1)? {
+                    return Ok(false);
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Whether an `includeIf` applies, or `None` for conditions which need `git`.
+    fn include_condition_matches(&mut self, condition: &str, config_path: &Path) -> Option<bool> {
+        if let Some(pattern) = condition.strip_prefix("hasconfig:remote.*.url:") {
+            self.has_remote_url_conditions = true;
+
+            // Matched the same way as `match_url`, so generated includes agree with identity resolution
+            return Some(self.remote_urls.as_ref().map_or(false, |urls| {
+                urls.iter().any(|url| url_matches_pattern(pattern, url))
+            }));
+        }
+
+        if let Some(pattern) = condition.strip_prefix("gitdir:") {
+            return self.git_dir_matches(pattern, config_path, true);
+        }
+
+        if let Some(pattern) = condition.strip_prefix("gitdir/i:") {
+            return self.git_dir_matches(pattern, config_path, false);
+        }
+
+        if let Some(pattern) = condition.strip_prefix("onbranch:") {
+            let branch = match self.repository {
+                Some(repository) if !repository.branch.is_empty() => &repository.branch,
+                _ => return Some(false),
+            };
+
+            let mut pattern = pattern.to_string();
+            if pattern.ends_with('/') {
+                pattern.push_str("**");
+            }
+
+            return glob_matches(pattern.as_str(), branch, true);
+        }
+
+        None
+    }
+
+    fn git_dir_matches(
+        &self,
+        pattern: &str,
+        config_path: &Path,
+        case_sensitive: bool,
+    ) -> Option<bool> {
+        let repository = match self.repository {
+            Some(repository) => repository,
+            None => return Some(false),
+        };
+
+        let mut pattern = match pattern.strip_prefix("./") {
+            Some(relative) => config_path.parent()?.join(relative).display().to_string(),
+            None => expand_tilde(pattern),
+        };
+
+        if !Path::new(&pattern).is_absolute() {
+            pattern.insert_str(0, "**/");
+        }
+        if pattern.ends_with('/') {
+            pattern.push_str("**");
+        }
+
+        glob_matches(
+            pattern.as_str(),
+            repository.git_dir.display().to_string().as_str(),
+            case_sensitive,
+        )
+    }
+}
+
+/// The URLs of every remote as written, before any `insteadOf` rewriting, which is what `hasconfig` matches
+fn collect_remote_urls(entries: &[ConfigEntry]) -> Vec<String> {
+    entries
+        .iter()
+        .filter(|e| matches!(split_key(e.key.as_str()), Some(("remote", _, "url"))))
+        .filter_map(|e| e.value.clone())
+        .collect()
+}
+
+fn glob_matches(pattern: &str, value: &str, case_sensitive: bool) -> Option<bool> {
+    let options = MatchOptions {
+        case_sensitive,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    };
+
+    Pattern::new(pattern)
+        .ok()
+        .map(|p| p.matches_with(value, options))
+}
+
+fn resolve_include_path(include_path: &str, config_path: &Path) -> PathBuf {
+    let expanded = PathBuf::from(expand_tilde(include_path));
+    if expanded.is_absolute() {
+        return expanded;
+    }
+
+    // Relative includes are relative to the file which includes them
+    config_path
+        .parent()
+        .map(|dir| dir.join(&expanded))
+        .unwrap_or(expanded)
+}
+
+fn expand_tilde(path: &str) -> String {
+    match (path.strip_prefix("~/"), home::home_dir()) {
+        (Some(rest), Some(home_dir)) => home_dir.join(rest).display().to_string(),
+        _ => path.to_string(),
+    }
+}
+
+/// The last value set for the key, `Some(None)` if it was set without a value
+fn lookup<'a>(entries: &'a [ConfigEntry], key: &str) -> Option<Option<&'a String>> {
+    entries
+        .iter()
+        .rev()
+        .find(|e| e.key == key)
+        .map(|e| e.value.as_ref())
+}
+
+fn is_true(value: Option<&String>) -> bool {
+    match value {
+        None => true,
+        Some(value) => {
+            matches!(value.to_lowercase().as_str(), "true" | "yes" | "on")
+                || value.parse::<i64>().map_or(false, |n| n != 0)
+        }
+    }
+}
+
+/// Lower case the section and name of a key, leaving the subsection alone as Git does
+fn normalize_key(key: &str) -> String {
+    match (key.find('.'), key.rfind('.')) {
+        (Some(first), Some(last)) if first != last => format!(
+            "{}{}{}",
+            key[..first].to_lowercase(),
+            &key[first..last],
+            key[last..].to_lowercase()
+        ),
+        _ => key.to_lowercase(),
+    }
+}
+
+/// Split `section.subsection.name`, `None` when there's no subsection
+fn split_key(key: &str) -> Option<(&str, &str, &str)> {
+    let first = key.find('.')?;
+    let last = key.rfind('.')?;
+    if first == last {
+        return None;
+    }
+
+    Some((&key[..first], &key[first + 1..last], &key[last + 1..]))
+}
+
+/// Parse the Git config format, see the CONFIGURATION FILE section of `git help config`
+/// Parse `GIT_CONFIG_PARAMETERS`, where each parameter is shell quoted, either as `'key'='value'` or, from older
+/// versions of Git, `'key=value'`. A key with no `=` at all is a boolean set to true.
+fn parse_parameters(parameters: &str) -> Option<Vec<(String, Option<String>)>> {
+    let mut entries = vec![];
+    let mut chars = parameters.chars();
+
+    let mut key = String::new();
+    let mut value: Option<String> = None;
+    let mut started = false;
+    let mut separated = false;
+    loop {
+        let c = chars.next();
+        let part = match c {
+            None | Some(' ') => {
+                if started {
+                    let (key, value) = match (separated, key.split_once('=')) {
+                        (false, Some((key, value))) => (key.to_string(), Some(value.to_string())),
+                        _ => (key.clone(), value.take()),
+                    };
+                    entries.push((normalize_key(key.as_str()), value));
+                }
+                if c.is_none() {
+                    break;
+                }
+
+                key.clear();
+                value = None;
+                started = false;
+                separated = false;
+                continue;
+            }
+            Some('=') if !separated => {
+                separated = true;
+                started = true;
+                continue;
+            }
+            Some('\'') => {
+                let mut part = String::new();
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => part.push(c),
+                    }
+                }
+                part
+            }
+            // Git escapes `'` and `!` outside of the quotes
+            Some('\\') => chars.next()?.to_string(),
+            Some(_) => return None,
+        };
+
+        started = true;
+        if separated {
+            value
+                .get_or_insert_with(String::new)
+                .push_str(part.as_str());
+        } else {
+            key.push_str(part.as_str());
+        }
+    }
+
+    Some(entries)
+}
+
+fn parse_config(content: &str) -> anyhow::Result<Vec<(String, Option<String>)>> {
+    let chars: Vec<char> = content.chars().collect();
+    let mut pos = 0;
+    let mut section = String::new();
+    let mut entries = vec![];
+
+    while pos < chars.len() {
+        let c = chars[pos];
+        if c.is_whitespace() {
+            pos += 1;
+        } else if c == '#' || c == ';' {
+            while pos < chars.len() && chars[pos] != '\n' {
+                pos += 1;
+            }
+        } else if c == '[' {
+            let end = chars[pos..]
+                .iter()
+                .position(|c| *c == ']' || *c == '\n')
+                .map(|i| pos + i)
+                .filter(|i| chars[*i] == ']')
+                .ok_or_else(|| anyhow!("Unterminated section header"))?;
+            section = parse_section_header(&chars[pos + 1..end])?;
+            pos = end + 1;
+        } else if c.is_ascii_alphabetic() {
+            if section.is_empty() {
+                return Err(anyhow!("Key outside of a section"));
+            }
+
+            let start = pos;
+            while pos < chars.len() && (chars[pos].is_ascii_alphanumeric() || chars[pos] == '-') {
+                pos += 1;
+            }
+            let name: String = chars[start..pos].iter().collect::<String>().to_lowercase();
+
+            while pos < chars.len() && (chars[pos] == ' ' || chars[pos] == '\t') {
+                pos += 1;
+            }
+
+            let value = match chars.get(pos) {
+                Some('=') => {
+                    let (value, next) = parse_value(&chars, pos + 1)?;
+                    pos = next;
+                    Some(value)
+                }
+                None | Some('\n') | Some('\r') | Some('#') | Some(';') => None,
+                Some(_) => return Err(anyhow!("Invalid key - {}", name)),
+            };
+
+            entries.push((format!("{}.{}", section, name), value));
+        } else {
+            return Err(anyhow!("Unexpected character - {:?}", c));
+        }
+    }
+
+    Ok(entries)
+}
+
+fn parse_section_header(header: &[char]) -> anyhow::Result<String> {
+    let header: String = header.iter().collect();
+
+    match header.find('"') {
+        // [section "subsection"], the subsection is case sensitive
+        Some(quote) => {
+            let name = header[..quote].trim();
+            let quoted = header[quote + 1..]
+                .strip_suffix('"')
+                .ok_or_else(|| anyhow!("Unterminated subsection - {}", header))?;
+
+            let mut subsection = String::new();
+            let mut escaped = false;
+            for c in quoted.chars() {
+                if escaped || c != '\\' {
+                    subsection.push(c);
+                    escaped = false;
+                } else {
+                    escaped = true;
+                }
+            }
+
+            Ok(format!("{}.{}", name.to_lowercase(), subsection))
+        }
+        // [section] or the deprecated [section.subsection]
+        None => Ok(header.trim().to_lowercase()),
+    }
+}
+
+/// Parse a value starting just after the `=`, returning it and the position after it
+fn parse_value(chars: &[char], mut pos: usize) -> anyhow::Result<(String, usize)> {
+    let mut value = String::new();
+    let mut quoted = false;
+    // Unquoted whitespace at the end of the value isn't part of it
+    let mut trailing_whitespace = 0;
+
+    while pos < chars.len() && (chars[pos] == ' ' || chars[pos] == '\t') {
+        pos += 1;
+    }
+
+    while pos < chars.len() {
+        let c = chars[pos];
+        pos += 1;
+
+        match c {
+            '\n' if !quoted => break,
+            '\n' => return Err(anyhow!("Newline in quoted value")),
+            '\r' => continue,
+            '\\' => {
+                let escaped = match chars.get(pos) {
+                    Some('\n') => None,
+                    Some('\r') if chars.get(pos + 1) == Some(&'\n') => {
+                        pos += 1;
+                        None
+                    }
+                    Some('n') => Some('\n'),
+                    Some('t') => Some('\t'),
+                    Some('b') => Some('\u{8}'),
+                    Some('"') => Some('"'),
+                    Some('\\') => Some('\\'),
+                    _ => return Err(anyhow!("Invalid escape in value")),
+                };
+                pos += 1;
+
+                if let Some(escaped) = escaped {
+                    value.push(escaped);
+                    trailing_whitespace = 0;
+                }
+            }
+            '"' => {
+                quoted = !quoted;
+                trailing_whitespace = 0;
+            }
+            '#' | ';' if !quoted => {
+                while pos < chars.len() && chars[pos] != '\n' {
+                    pos += 1;
+                }
+                break;
+            }
+            ' ' | '\t' if !quoted => {
+                value.push(c);
+                trailing_whitespace += 1;
+            }
+            _ => {
+                value.push(c);
+                trailing_whitespace = 0;
+            }
+        }
+    }
+
+    if quoted {
+        return Err(anyhow!("Unterminated quote in value"));
+    }
+
+    value.truncate(value.len() - trailing_whitespace);
+
+    Ok((value, pos))
+}
@@ -1,57 +1,317 @@
 use anyhow::{anyhow, Context};
-use std::fs::File;
+use std::env::current_dir;
 #[cfg(target_family = "unix")]
 use std::fs::Permissions;
-use std::io::Write;
+use std::fs::{create_dir_all, remove_file, rename, File};
+use std::io::{Read, Write};
 #[cfg(target_family = "unix")]
 use std::os::unix::fs::PermissionsExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::str::FromStr;
 
-const PRE_COMMIT_HOOK: &str = r#"#!/usr/bin/env bash
-identity git hook --pre-commit
-"#;
+pub const HOOK_BLOCK_START: &str = "# >>> identity >>>";
+pub const HOOK_BLOCK_END: &str = "# <<< identity <<<";
 
-pub fn run_git_install(force: bool) -> anyhow::Result<()> {
-    let hook_path = get_pre_commit_hook_path()?;
-    if hook_path.exists() && !force {
+/// A Git hook which `identity` can install
+pub struct Hook {
+    pub name: &'static str,
+    pub command: &'static str,
+    /// Git gives these hooks input on stdin, which has to be kept for any hook chained after ours
+    reads_stdin: bool,
+}
+
+pub const PRE_COMMIT: Hook = Hook {
+    name: "pre-commit",
+    command: "identity git hook --pre-commit",
+    reads_stdin: false,
+};
+
+pub const PRE_PUSH: Hook = Hook {
+    name: "pre-push",
+    command: "identity git hook --pre-push",
+    reads_stdin: true,
+};
+
+pub const COMMIT_MSG: Hook = Hook {
+    name: "commit-msg",
+    command: "identity git hook --commit-msg",
+    reads_stdin: false,
+};
+
+const HOOKS: [&Hook; 3] = [&PRE_COMMIT, &PRE_PUSH, &COMMIT_MSG];
+
+const HOOK_SHEBANG: &str = "#!/usr/bin/env bash";
+
+/// An existing hook which can't share a script with ours is moved aside to this name and run from our hook instead.
+const CHAINED_HOOK_SUFFIX: &str = "identity-chained";
+
+pub fn run_git_install(hooks: &[&Hook], force: bool) -> anyhow::Result<()> {
+    for hook in hooks {
+        install_hook(hook, force)?;
+    }
+
+    Ok(())
+}
+
+fn install_hook(hook: &Hook, force: bool) -> anyhow::Result<()> {
+    let hook_path = get_hook_path(hook.name)?;
+
+    if !hook_path.exists() || force {
+        // Forcing replaces the existing hook, including any which was moved aside to be chained
+        let chained_hook_path = chained_hook_path(&hook_path);
+        if chained_hook_path.exists() {
+            remove_file(&chained_hook_path).with_context(|| {
+                format!("Failed to remove chained hook - {:?}", chained_hook_path)
+            })?;
+            println!("Removed the chained hook at {:?}", chained_hook_path);
+        }
+
+        write_hook(&hook_path, new_hook_content(hook, None).as_str())?;
+        println!("Hook installed at {:?}", hook_path);
+        return Ok(());
+    }
+
+    let content = read_hook(&hook_path)?;
+
+    if content.contains(HOOK_BLOCK_START) {
+        let chained_hook_path = chained_hook_path(&hook_path);
+        let chained = chained_hook_path
+            .exists()
+            .then_some(chained_hook_path.as_path());
+        let content = insert_hook_block(
+            remove_hook_block(content.as_str()).as_str(),
+            hook_block(hook, chained).as_str(),
+        );
+        write_hook(&hook_path, content.as_str())?;
+        println!("Identity check updated in existing hook at {:?}", hook_path);
+    } else if content.contains(hook.command) {
+        println!("Identity check already present in hook at {:?}", hook_path);
+    } else if is_shell_script(content.as_str()) && !hook.reads_stdin {
+        let content = insert_hook_block(content.as_str(), hook_block(hook, None).as_str());
+        write_hook(&hook_path, content.as_str())?;
+        println!("Identity check added to existing hook at {:?}", hook_path);
+    } else {
+        let chained_hook_path = chained_hook_path(&hook_path);
+        rename(&hook_path, &chained_hook_path)
+            .with_context(|| format!("Failed to move existing hook aside - {:?}", hook_path))?;
+        write_hook(
+            &hook_path,
+            new_hook_content(hook, Some(&chained_hook_path)).as_str(),
+        )?;
+        println!(
+            "Hook installed at {:?}, the existing hook was moved to {:?} and will still be run",
+            hook_path, chained_hook_path
+        );
+    }
+
+    Ok(())
+}
+
+pub fn run_git_uninstall() -> anyhow::Result<()> {
+    let mut removed = false;
+    for hook in HOOKS {
+        removed |= uninstall_hook(hook)?;
+    }
+
+    if !removed {
         return Err(anyhow!(
-            "Pre-commit hook already exists, run with --force to overwrite"
+            "No hooks contain an identity check that can be removed"
+        ));
+    }
+
+    Ok(())
+}
+
+fn uninstall_hook(hook: &Hook) -> anyhow::Result<bool> {
+    let hook_path = get_hook_path(hook.name)?;
+    if !hook_path.exists() {
+        return Ok(false);
+    }
+
+    let chained_hook_path = chained_hook_path(&hook_path);
+    if chained_hook_path.exists() {
+        rename(&chained_hook_path, &hook_path).with_context(|| {
+            format!("Failed to restore existing hook - {:?}", chained_hook_path)
+        })?;
+        println!(
+            "Identity check removed, restored the original hook at {:?}",
+            hook_path
+        );
+        return Ok(true);
+    }
+
+    let content = read_hook(&hook_path)?;
+    if content.trim() == unmarked_hook_content(hook).trim() {
+        remove_file(&hook_path)
+            .with_context(|| format!("Failed to remove hook - {:?}", hook_path))?;
+        println!("Hook removed from {:?}", hook_path);
+        return Ok(true);
+    }
+
+    if !content.contains(HOOK_BLOCK_START) {
+        return Ok(false);
+    }
+
+    let content = remove_hook_block(content.as_str());
+    if content.trim() == HOOK_SHEBANG {
+        remove_file(&hook_path)
+            .with_context(|| format!("Failed to remove hook - {:?}", hook_path))?;
+        println!("Hook removed from {:?}", hook_path);
+    } else {
+        write_hook(&hook_path, content.as_str())?;
+        println!("Identity check removed from hook at {:?}", hook_path);
+    }
+
+    Ok(true)
+}
+
+/// The lines between the block markers, if the hook has a complete identity block.
+pub fn get_hook_block(content: &str) -> Option<Vec<&str>> {
+    let mut lines = content
+        .lines()
+        .skip_while(|l| l.trim() != HOOK_BLOCK_START)
+        .skip(1);
+
+    let mut block = vec![];
+    for line in lines.by_ref() {
+        if line.trim() == HOOK_BLOCK_END {
+            return Some(block);
+        }
+        block.push(line);
+    }
+
+    None
+}
+
+fn hook_block(hook: &Hook, chained_hook_path: Option<&Path>) -> String {
+    let chained_command = chained_hook_path.map(|p| {
+        format!(
+            "\"$(dirname \"$0\")/{}\" \"$@\"",
+            p.file_name().unwrap().to_string_lossy()
+        )
+    });
+
+    let mut lines = vec![HOOK_BLOCK_START.to_string()];
+    if hook.reads_stdin {
+        let replay_stdin = r#"{ [ -z "$identity_stdin" ] || printf '%s\n' "$identity_stdin"; }"#;
+        lines.push(r#"identity_stdin="$(cat)""#.to_string());
+        lines.push(format!(
+            "{} | {} \"$@\" || exit $?",
+            replay_stdin, hook.command
         ));
+        if let Some(c) = chained_command {
+            lines.push(format!("{} | {}", replay_stdin, c));
+            lines.push("exit $?".to_string());
+        }
+    } else {
+        lines.push(format!("{} \"$@\" || exit $?", hook.command));
+        if let Some(c) = chained_command {
+            lines.push(format!("exec {}", c));
+        }
+    }
+    lines.push(HOOK_BLOCK_END.to_string());
+
+    lines.join("\n") + "\n"
+}
+
+fn new_hook_content(hook: &Hook, chained_hook_path: Option<&Path>) -> String {
+    format!("{}\n{}", HOOK_SHEBANG, hook_block(hook, chained_hook_path))
+}
+
+/// Hooks were written like this before the check was put in a marked block
+fn unmarked_hook_content(hook: &Hook) -> String {
+    format!("{}\n{}\n", HOOK_SHEBANG, hook.command)
+}
+
+/// Insert the block straight after the shebang so that it runs even if the rest of the script exits early.
+fn insert_hook_block(content: &str, block: &str) -> String {
+    match content.split_once('\n') {
+        Some((first, rest)) if first.starts_with("#!") => format!("{}\n{}{}", first, block, rest),
+        None if content.starts_with("#!") => format!("{}\n{}", content, block),
+        _ => format!("{}{}", block, content),
     }
+}
+
+fn remove_hook_block(content: &str) -> String {
+    let mut in_block = false;
+    let mut result = String::new();
+
+    for line in content.lines() {
+        if line.trim() == HOOK_BLOCK_START {
+            in_block = true;
+        } else if line.trim() == HOOK_BLOCK_END && in_block {
+            in_block = false;
+        } else if !in_block {
+            result.push_str(line);
+            result.push('\n');
+        }
+    }
+
+    result
+}
+
+/// Git runs hooks without a shebang with `sh`, so those count as shell scripts too.
+fn is_shell_script(content: &str) -> bool {
+    let shebang = match content.lines().next().and_then(|l| l.strip_prefix("#!")) {
+        Some(s) => s,
+        None => return true,
+    };
+
+    let mut parts = shebang.split_whitespace();
+    let interpreter = match parts.next() {
+        Some(p) if p.ends_with("/env") => parts.next(),
+        p => p,
+    };
+
+    interpreter
+        .and_then(|i| i.rsplit('/').next())
+        .map(|i| ["sh", "bash", "zsh", "dash", "ksh"].contains(&i))
+        .unwrap_or(false)
+}
 
-    let mut f = File::create(&hook_path)
-        .with_context(|| format!("Failed to create pre-commit hook script - {:?}", hook_path))?;
+fn chained_hook_path(hook_path: &Path) -> PathBuf {
+    hook_path.with_extension(CHAINED_HOOK_SUFFIX)
+}
+
+fn read_hook(hook_path: &Path) -> anyhow::Result<String> {
+    let mut content = String::new();
+    File::open(hook_path)
+        .with_context(|| format!("Failed to open hook script - {:?}", hook_path))?
+        .read_to_string(&mut content)
+        .with_context(|| format!("Failed to read hook script - {:?}", hook_path))?;
+
+    Ok(content)
+}
+
+fn write_hook(hook_path: &Path, content: &str) -> anyhow::Result<()> {
+    // Bare repositories and `core.hooksPath` may not have a hooks directory yet
+    if let Some(hooks_dir) = hook_path.parent() {
+        create_dir_all(hooks_dir)
+            .with_context(|| format!("Failed to create hooks directory - {:?}", hooks_dir))?;
+    }
+
+    let mut f = File::create(hook_path)
+        .with_context(|| format!("Failed to create hook script - {:?}", hook_path))?;
     #[cfg(target_family = "unix")]
     f.set_permissions(Permissions::from_mode(0o755))?;
 
-    f.write_all(PRE_COMMIT_HOOK.as_bytes())
-        .with_context(|| format!("Failed to write pre-commit hook script - {:?}", hook_path))?;
-
-    println!("Hook installed at {:?}", hook_path);
+    f.write_all(content.as_bytes())
+        .with_context(|| format!("Failed to write hook script - {:?}", hook_path))?;
 
     Ok(())
 }
 
-pub fn get_pre_commit_hook_path() -> anyhow::Result<PathBuf> {
-    let git_root = String::from_utf8(
-        Command::new("git")
-            .args(["rev-parse", "--show-toplevel"])
-            .output()?
-            .stdout,
-    )?
-    .trim_end()
-    .to_string();
+/// Ask Git where hooks live, which accounts for `core.hooksPath`, linked worktrees, submodules and bare repositories.
+pub fn get_hook_path(hook_name: &str) -> anyhow::Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .output()?;
 
-    if git_root.is_empty() {
+    let hooks_dir = String::from_utf8(output.stdout)?.trim_end().to_string();
+    if !output.status.success() || hooks_dir.is_empty() {
         return Err(anyhow!("Not in a Git repository"));
     }
 
-    let hook_path = PathBuf::from_str(git_root.as_str())?
-        .join(".git")
-        .join("hooks")
-        .join("pre-commit");
-
-    Ok(hook_path)
+    // The path may be relative to the current directory
+    Ok(current_dir()?.join(hooks_dir).join(hook_name))
 }
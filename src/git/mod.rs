@@ -1,16 +1,34 @@
+mod audit;
+mod backend;
 mod check;
 mod cli;
+mod clone;
+mod commits;
 mod common;
 mod credentials;
+mod framework;
+mod history;
 mod hook;
+mod in_process;
 mod install;
+mod prompt;
+mod recursive;
+mod resolve;
 mod run;
-mod whoami;
+mod signing;
+mod ssh;
+mod submodules;
 mod switch;
+mod sync;
+mod whoami;
 
+pub use backend::get_backend;
 pub use cli::configure;
+pub use credentials::sync_credentials;
+pub use prompt::get_prompt_token;
 pub use run::run_git;
+pub use submodules::run_git_switch_submodules;
+pub use switch::{apply_switch, prepare_switch, ConfigScope, CONFIG_SCOPES};
 pub use whoami::run_who_am_i;
-pub use switch::{prepare_switch, apply_switch};
 
 pub const GIT_SERVICE: &str = "git";
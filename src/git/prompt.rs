@@ -0,0 +1,26 @@
+use crate::config::LazyConfig;
+use crate::git::backend::GitBackend;
+use crate::git::common::{get_email, get_username};
+use crate::git::resolve::resolve_identity;
+
+/// The identity for the current repository, prefixed with `!` if Git is configured differently.
+/// Empty if no identity matches the repository.
+pub fn get_prompt_token(
+    config: &mut LazyConfig,
+    backend: &dyn GitBackend,
+) -> anyhow::Result<String> {
+    config.required()?;
+
+    let identity = match resolve_identity(config, backend) {
+        Ok(identity) => identity,
+        Err(_) => return Ok(String::new()),
+    };
+
+    if identity.user() == Some(&get_username(backend)?)
+        && identity.email() == Some(&get_email(backend)?)
+    {
+        Ok(identity.id().to_string())
+    } else {
+        Ok(format!("!{}", identity.id()))
+    }
+}
@@ -0,0 +1,226 @@
+use crate::config::LazyConfig;
+use crate::git::backend::get_backend;
+use crate::git::check::check_hook_content;
+use crate::git::hook::get_config_mismatches;
+use crate::git::install::{run_git_install, Hook};
+use crate::git::resolve::resolve_identity;
+use crate::git::switch::{apply_switch, ConfigScope};
+use anyhow::{anyhow, Context};
+use std::env::{current_dir, set_current_dir};
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+/// One row of the summary printed after working through every repository
+struct RepositoryReport {
+    repository: String,
+    identity: String,
+    status: String,
+    ok: bool,
+}
+
+pub fn run_git_install_recursive(
+    config: &mut LazyConfig,
+    root: &str,
+    hooks: &[&Hook],
+    force: bool,
+) -> anyhow::Result<()> {
+    config.required()?;
+
+    let reports = for_each_repository(root, |repository| {
+        if let Err(e) = run_git_install(hooks, force) {
+            return report(repository, "-", format!("install failed - {}", e), false);
+        }
+
+        let backend = get_backend();
+        match resolve_identity(config, backend.as_ref()) {
+            Ok(identity) => match apply_switch(backend.as_ref(), &identity, ConfigScope::Local) {
+                Ok(()) => report(repository, identity.id(), "installed".to_string(), true),
+                Err(e) => report(
+                    repository,
+                    identity.id(),
+                    format!("switch failed - {}", e),
+                    false,
+                ),
+            },
+            Err(_) => report(
+                repository,
+                "-",
+                "installed, no identity matched".to_string(),
+                false,
+            ),
+        }
+    })?;
+
+    print_summary(&reports);
+
+    Ok(())
+}
+
+pub fn run_git_check_recursive(config: &mut LazyConfig, root: &str) -> anyhow::Result<()> {
+    config.required()?;
+
+    let reports = for_each_repository(root, |repository| {
+        let backend = get_backend();
+        let identity = match resolve_identity(config, backend.as_ref()) {
+            Ok(identity) => identity,
+            Err(_) => return report(repository, "-", "no identity matched".to_string(), false),
+        };
+
+        // A default identity is worth pointing out, the repository may be missing a rule
+        let id = if identity.is_default() {
+            format!("{} (default)", identity.id())
+        } else {
+            identity.id().to_string()
+        };
+
+        let mut problems = vec![];
+        if let Err(e) = check_hook_content(backend.as_ref()) {
+            problems.push(e.to_string());
+        }
+        match get_config_mismatches(backend.as_ref(), &identity) {
+            Ok(mismatches) if mismatches.is_empty() => {}
+            Ok(_) => problems.push("identity mismatch".to_string()),
+            Err(e) => problems.push(e.to_string()),
+        }
+
+        if problems.is_empty() {
+            report(repository, id.as_str(), "ok".to_string(), true)
+        } else {
+            report(repository, id.as_str(), problems.join(", "), false)
+        }
+    })?;
+
+    print_summary(&reports);
+
+    if reports.iter().any(|r| !r.ok) {
+        exit(1);
+    }
+
+    Ok(())
+}
+
+fn report(repository: &str, identity: &str, status: String, ok: bool) -> RepositoryReport {
+    RepositoryReport {
+        repository: repository.to_string(),
+        identity: identity.to_string(),
+        status,
+        ok,
+    }
+}
+
+/// Run `action` inside every repository under `root`, everything else here works on the current directory
+fn for_each_repository<F>(root: &str, mut action: F) -> anyhow::Result<Vec<RepositoryReport>>
+where
+    F: FnMut(&str) -> RepositoryReport,
+{
+    let root_path = Path::new(root);
+    if !root_path.is_dir() {
+        return Err(anyhow!("Not a directory - {:?}", root_path));
+    }
+
+    let mut repositories = vec![];
+    find_repositories(root_path, &mut repositories)?;
+    repositories.sort();
+
+    let original_dir = current_dir()?;
+    let mut reports = vec![];
+    for repository in repositories {
+        let name = repository
+            .strip_prefix(root_path)
+            .ok()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(repository.as_path())
+            .display()
+            .to_string();
+
+        set_current_dir(&repository)
+            .with_context(|| format!("Failed to enter repository - {:?}", repository))?;
+        reports.push(action(name.as_str()));
+    }
+    set_current_dir(original_dir)?;
+
+    Ok(reports)
+}
+
+/// Directories which are full of other people's checkouts rather than your own
+const SKIPPED_DIRS: [&str; 3] = ["node_modules", "target", "vendor"];
+
+/// Collect the repositories under `dir`, without looking inside them for more
+fn find_repositories(dir: &Path, repositories: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    // A file is how linked worktrees and submodules point at their Git directory
+    if dir.join(".git").exists() {
+        repositories.push(dir.to_path_buf());
+        return Ok(());
+    }
+
+    // One directory you can't read shouldn't stop the rest from being found
+    let entries = match read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Skipping directory which can't be read - {:?} - {}", dir, e);
+            return Ok(());
+        }
+    };
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("Skipping entry which can't be read in {:?} - {}", dir, e);
+                continue;
+            }
+        };
+
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref()) {
+            continue;
+        }
+
+        // Following symlinks risks visiting the same repository twice, or looping forever
+        if entry.file_type().map_or(false, |t| t.is_dir()) {
+            find_repositories(&entry.path(), repositories)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn print_summary(reports: &[RepositoryReport]) {
+    if reports.is_empty() {
+        println!("No Git repositories found");
+        return;
+    }
+
+    let repository_width = reports
+        .iter()
+        .map(|r| r.repository.len())
+        .max()
+        .unwrap_or(0)
+        .max("REPOSITORY".len());
+    let identity_width = reports
+        .iter()
+        .map(|r| r.identity.len())
+        .max()
+        .unwrap_or(0)
+        .max("IDENTITY".len());
+
+    println!(
+        "\n{:<repository_width$}  {:<identity_width$}  STATUS",
+        "REPOSITORY", "IDENTITY"
+    );
+    for r in reports {
+        println!(
+            "{:<repository_width$}  {:<identity_width$}  {}",
+            r.repository, r.identity, r.status
+        );
+    }
+
+    let problems = reports.iter().filter(|r| !r.ok).count();
+    println!(
+        "\n{} repositories, {} with problems",
+        reports.len(),
+        problems
+    );
+}
@@ -0,0 +1,139 @@
+use crate::config::Config;
+use crate::git::backend::GitBackend;
+use crate::git::common::get_toplevel;
+use crate::git::GIT_SERVICE;
+use crate::identity::Identity;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Remotes are tried in this order when the config doesn't give a preference
+const DEFAULT_REMOTES: [&str; 1] = ["origin"];
+
+pub struct Remote {
+    pub name: String,
+    pub url: String,
+}
+
+/// Find the identity for the current repository, by its remote URLs or failing that by where it is on disk.
+pub fn resolve_identity<'a>(
+    config: &'a Config,
+    backend: &dyn GitBackend,
+) -> anyhow::Result<Identity<'a>> {
+    Ok(resolve_identity_and_url(config, backend)?.0)
+}
+
+/// As `resolve_identity`, along with the URL of the remote which decided it. That's the remote Git will be talking to,
+/// so it's the one whose credentials matter. Empty when the repository has no remotes.
+pub fn resolve_identity_and_url<'a>(
+    config: &'a Config,
+    backend: &dyn GitBackend,
+) -> anyhow::Result<(Identity<'a>, String)> {
+    let remotes = get_ordered_remotes(config, backend)?;
+
+    // The first remote which matches by URL wins, so a fork's upstream can't be shadowed by an unknown origin
+    let url = remotes
+        .iter()
+        .find(|r| {
+            !config
+                .identities_for_url(GIT_SERVICE, r.url.as_str())
+                .is_empty()
+        })
+        .or_else(|| remotes.first())
+        .map(|r| r.url.clone())
+        .unwrap_or_default();
+
+    let identity = resolve_identity_for_url(config, backend, url.as_str())?;
+
+    Ok((identity, url))
+}
+
+/// Find the identity for a remote of the current repository, which may not be the origin.
+pub fn resolve_identity_for_url<'a>(
+    config: &'a Config,
+    backend: &dyn GitBackend,
+    url: &str,
+) -> anyhow::Result<Identity<'a>> {
+    let toplevel = get_toplevel(backend)?;
+
+    let path = if toplevel.is_empty() {
+        None
+    } else {
+        Some(Path::new(toplevel.as_str()))
+    };
+
+    config.account_for_repository(GIT_SERVICE, url, path)
+}
+
+/// Remotes which match different identities by URL, with the identity each one matches.
+/// Resolution picks the first in order, but the others are likely a mistake worth reporting.
+pub fn get_remote_conflicts(
+    config: &Config,
+    backend: &dyn GitBackend,
+) -> anyhow::Result<Vec<(String, String)>> {
+    let matched: Vec<(String, String)> = get_ordered_remotes(config, backend)?
+        .into_iter()
+        .filter_map(|r| {
+            let identities = config.identities_for_url(GIT_SERVICE, r.url.as_str());
+            match identities.as_slice() {
+                [identity] => Some((r.name, identity.id().to_string())),
+                _ => None,
+            }
+        })
+        .collect();
+
+    let distinct: BTreeMap<&String, &String> = matched.iter().map(|(r, id)| (id, r)).collect();
+    if distinct.len() > 1 {
+        Ok(matched)
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// The repository's remotes in the order they should be tried. The remote the current branch tracks comes first,
+/// then the configured preference list, then any others.
+pub fn get_ordered_remotes(
+    config: &Config,
+    backend: &dyn GitBackend,
+) -> anyhow::Result<Vec<Remote>> {
+    let mut names = backend.get_remotes()?;
+
+    let preferred: Vec<String> = match config.git.as_ref().and_then(|g| g.remotes.as_ref()) {
+        Some(remotes) => remotes.clone(),
+        None => DEFAULT_REMOTES.iter().map(|r| r.to_string()).collect(),
+    };
+
+    let mut order = vec![];
+    if let Some(upstream) = get_upstream_remote(backend)? {
+        order.push(upstream);
+    }
+    order.extend(preferred);
+
+    let mut ordered = vec![];
+    for name in order {
+        if let Some(index) = names.iter().position(|n| *n == name) {
+            ordered.push(names.remove(index));
+        }
+    }
+    ordered.extend(names);
+
+    ordered
+        .into_iter()
+        .map(|name| {
+            let url = backend.get_remote_url(name.as_str())?;
+            Ok(Remote { name, url })
+        })
+        .collect()
+}
+
+/// The remote the current branch pushes and pulls from, if it tracks one.
+fn get_upstream_remote(backend: &dyn GitBackend) -> anyhow::Result<Option<String>> {
+    let branch = backend.get_current_branch()?;
+    if branch.is_empty() {
+        return Ok(None);
+    }
+
+    let remote = backend.get_config(format!("branch.{}.remote", branch).as_str())?;
+
+    // `.` means the branch tracks another local branch
+    Ok(Some(remote).filter(|r| !r.is_empty() && r != "."))
+}
@@ -1,8 +1,18 @@
-use crate::config::LazyConfig;
+use crate::config::{HookMode, LazyConfig};
+use crate::git::audit::run_git_audit;
 use crate::git::check::run_git_check;
 use crate::git::cli;
-use crate::git::hook::run_git_pre_commit_hook;
-use crate::git::install::run_git_install;
+use crate::git::clone::run_git_clone;
+use crate::git::credentials::{run_git_credential, run_git_sync_credentials};
+use crate::git::framework::{run_git_install_framework, Framework};
+use crate::git::history::run_git_fix_history;
+use crate::git::hook::{
+    run_git_chpwd_hook, run_git_commit_msg_hook, run_git_pre_commit_hook, run_git_pre_push_hook,
+};
+use crate::git::install::{run_git_install, run_git_uninstall, COMMIT_MSG, PRE_COMMIT, PRE_PUSH};
+use crate::git::recursive::{run_git_check_recursive, run_git_install_recursive};
+use crate::git::submodules::run_git_install_submodules;
+use crate::git::sync::run_git_sync_global;
 use anyhow::anyhow;
 use clap::ArgMatches;
 
@@ -12,12 +22,88 @@ pub fn run_git(config: &mut LazyConfig, arg_matches: &ArgMatches) -> anyhow::Res
     }
 
     match arg_matches.subcommand() {
-        Some(("install", sub_matches)) => run_git_install(sub_matches.get_flag("force")),
+        Some(("install", sub_matches)) => match sub_matches.get_one::<String>("framework") {
+            Some(framework) => run_git_install_framework(Framework::from_name(framework)?),
+            None => {
+                let mut hooks = vec![&PRE_COMMIT];
+                if sub_matches.get_flag("pre-push") {
+                    hooks.push(&PRE_PUSH);
+                }
+                if sub_matches.get_flag("commit-msg") {
+                    hooks.push(&COMMIT_MSG);
+                }
+                match sub_matches.get_one::<String>("recursive") {
+                    Some(root) => run_git_install_recursive(
+                        config,
+                        root,
+                        &hooks,
+                        sub_matches.get_flag("force"),
+                    ),
+                    None if sub_matches.get_flag("recurse-submodules") => {
+                        run_git_install_submodules(&hooks, sub_matches.get_flag("force"))
+                    }
+                    None => run_git_install(&hooks, sub_matches.get_flag("force")),
+                }
+            }
+        },
+        Some(("check", sub_matches)) => match sub_matches.get_one::<String>("recursive") {
+            Some(root) => run_git_check_recursive(config, root),
+            None => run_git_check(config),
+        },
+        Some(("uninstall", _)) => run_git_uninstall(),
+        Some(("sync-global", _)) => run_git_sync_global(config),
+        Some(("audit", sub_matches)) => run_git_audit(
+            config,
+            sub_matches.get_one::<String>("since"),
+            sub_matches.get_flag("branch"),
+            sub_matches.get_flag("json"),
+        ),
+        Some(("fix-history", sub_matches)) => run_git_fix_history(
+            config,
+            sub_matches.get_flag("dry-run"),
+            sub_matches.get_flag("yes"),
+        ),
+        Some(("clone", sub_matches)) => run_git_clone(
+            config,
+            sub_matches.get_one::<String>("URL").unwrap(),
+            sub_matches.get_one::<String>("DIRECTORY"),
+        ),
+        Some(("sync-credentials", _)) => run_git_sync_credentials(config),
+        Some(("credential", sub_matches)) => {
+            run_git_credential(config, sub_matches.get_one::<String>("OPERATION").unwrap())
+        }
         Some(("hook", sub_matches)) => {
+            let hook_args: Vec<&String> = sub_matches
+                .get_many::<String>("HOOK_ARGS")
+                .map(|a| a.collect())
+                .unwrap_or_default();
+            let mode = sub_matches
+                .get_one::<String>("mode")
+                .map(|m| HookMode::from_name(m))
+                .transpose()?;
+
             if sub_matches.get_flag("pre-commit") {
-                run_git_pre_commit_hook(config)
+                run_git_pre_commit_hook(config, mode)
+            } else if sub_matches.get_flag("pre-push") {
+                run_git_pre_push_hook(
+                    config,
+                    mode,
+                    hook_args.first().cloned(),
+                    hook_args.get(1).cloned(),
+                )
+            } else if sub_matches.get_flag("commit-msg") {
+                run_git_commit_msg_hook(
+                    config,
+                    mode,
+                    hook_args.first().cloned(),
+                    sub_matches.get_flag("rewrite"),
+                )
+            } else if sub_matches.get_flag("chpwd") {
+                run_git_chpwd_hook(config, sub_matches.get_flag("apply"))
             } else {
-                Err(anyhow!("Only `--pre-commit` is supported"))
+                Err(anyhow!(
+                    "Only `--pre-commit`, `--pre-push`, `--commit-msg` and `--chpwd` are supported"
+                ))
             }
         }
         Some(_) | None => {
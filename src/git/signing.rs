@@ -0,0 +1,56 @@
+use crate::config::{expand_home, SigningFormat};
+use crate::git::backend::GitBackend;
+use crate::git::common::{get_bool_config, get_config};
+use crate::identity::Identity;
+
+/// Every key `signing_config` sets, so they can be cleared for an identity which doesn't sign
+pub const SIGNING_CONFIG_KEYS: [&str; 3] = ["user.signingkey", "gpg.format", "commit.gpgsign"];
+
+/// The Git config needed to sign commits as this identity, empty if it doesn't have a signing key.
+pub fn signing_config(identity: &Identity) -> Vec<(&'static str, String)> {
+    let signing_key = match identity.signing_key() {
+        Some(k) => k,
+        None => return vec![],
+    };
+
+    let signing_format = identity.signing_format().unwrap_or(SigningFormat::Gpg);
+
+    // SSH signing keys are paths, GPG and X.509 keys are ids
+    let signing_key = match signing_format {
+        SigningFormat::Ssh => expand_home(signing_key),
+        _ => signing_key.clone(),
+    };
+
+    vec![
+        ("user.signingkey", signing_key),
+        ("gpg.format", signing_format.git_format().to_string()),
+        ("commit.gpgsign", "true".to_string()),
+    ]
+}
+
+pub fn signing_mismatches(
+    backend: &dyn GitBackend,
+    identity: &Identity,
+) -> anyhow::Result<Vec<String>> {
+    let mut mismatches = vec![];
+
+    for (key, expected) in signing_config(identity) {
+        let actual = match key {
+            "commit.gpgsign" => get_bool_config(backend, key)?.to_string(),
+            // Git signs with OpenPGP unless told otherwise
+            "gpg.format" => Some(get_config(backend, key)?)
+                .filter(|f| !f.is_empty())
+                .unwrap_or_else(|| SigningFormat::Gpg.git_format().to_string()),
+            _ => get_config(backend, key)?,
+        };
+
+        if actual != expected {
+            mismatches.push(format!(
+                "Signing config mismatch for {} - expected={} != actual={}",
+                key, expected, actual
+            ));
+        }
+    }
+
+    Ok(mismatches)
+}
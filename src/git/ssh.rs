@@ -0,0 +1,61 @@
+use crate::config::expand_home;
+use crate::git::backend::GitBackend;
+use crate::git::common::get_ssh_command;
+use crate::identity::Identity;
+use anyhow::{anyhow, Context};
+#[cfg(target_family = "unix")]
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+/// The `core.sshCommand` which makes Git use only the given key.
+pub fn ssh_command_for_key(ssh_key: &str) -> String {
+    let ssh_key = expand_home(ssh_key);
+
+    // Git runs the command through a shell
+    let ssh_key = if ssh_key.contains(|c: char| c.is_whitespace() || c == '\'') {
+        format!("'{}'", ssh_key.replace('\'', r"'\''"))
+    } else {
+        ssh_key
+    };
+
+    format!("ssh -i {} -o IdentitiesOnly=yes", ssh_key)
+}
+
+/// Whether a `core.sshCommand` looks like one written by [ssh_command_for_key].
+pub fn is_managed_ssh_command(ssh_command: &str) -> bool {
+    ssh_command.starts_with("ssh -i ") && ssh_command.ends_with(" -o IdentitiesOnly=yes")
+}
+
+pub fn check_ssh_key(backend: &dyn GitBackend, identity: &Identity) -> anyhow::Result<()> {
+    let ssh_key = match identity.ssh_key() {
+        Some(k) => k,
+        None => return Ok(()),
+    };
+
+    let key_path = expand_home(ssh_key);
+    let metadata = Path::new(key_path.as_str())
+        .metadata()
+        .with_context(|| format!("SSH key not found - {}", key_path))?;
+
+    #[cfg(target_family = "unix")]
+    if metadata.permissions().mode() & 0o077 != 0 {
+        return Err(anyhow!(
+            "SSH key is accessible by other users, it should have permissions 0600 - {}",
+            key_path
+        ));
+    }
+    #[cfg(not(target_family = "unix"))]
+    let _ = metadata;
+
+    let expected = ssh_command_for_key(ssh_key);
+    let actual = get_ssh_command(backend)?;
+    if expected != actual {
+        return Err(anyhow!(
+            "SSH command mismatch - expected={} != actual={}",
+            expected,
+            actual
+        ));
+    }
+
+    Ok(())
+}
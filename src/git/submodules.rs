@@ -0,0 +1,97 @@
+use crate::config::Config;
+use crate::git::backend::get_backend;
+use crate::git::credentials::sync_credentials;
+use crate::git::install::{run_git_install, Hook};
+use crate::git::resolve::resolve_identity_and_url;
+use crate::git::switch::{apply_switch, ConfigScope};
+use anyhow::Context;
+use std::env::{current_dir, set_current_dir};
+use std::process::Command;
+
+/// Apply the identity matched for each submodule's own remotes, submodules often belong to a different organisation
+pub fn run_git_switch_submodules(config: &Config, scope: ConfigScope) -> anyhow::Result<()> {
+    for_each_submodule(|submodule| {
+        let backend = get_backend();
+        let (identity, url) = match resolve_identity_and_url(config, backend.as_ref()) {
+            Ok(resolved) => resolved,
+            Err(_) => {
+                println!(
+                    "No identity matched submodule `{}`, leaving it alone",
+                    submodule
+                );
+                return Ok(());
+            }
+        };
+
+        apply_switch(backend.as_ref(), &identity, scope)
+            .with_context(|| format!("Failed to switch submodule - {:?}", submodule))?;
+        if sync_credentials(backend.as_ref(), &identity, url.as_str())? {
+            println!(
+                "Stored the token for `{}` in your Git credentials helper",
+                identity.id()
+            );
+        }
+        println!("Applied `{}` to submodule `{}`", identity.id(), submodule);
+
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+pub fn run_git_install_submodules(hooks: &[&Hook], force: bool) -> anyhow::Result<()> {
+    run_git_install(hooks, force)?;
+
+    for_each_submodule(|submodule| {
+        println!("Submodule `{}`", submodule);
+        run_git_install(hooks, force)
+            .with_context(|| format!("Failed to install in submodule - {:?}", submodule))
+    })?;
+
+    Ok(())
+}
+
+/// Run `action` inside every checked out submodule, nested ones included, with its path relative to the current
+/// directory. Everything else here works on the current directory, so it's put back afterwards even on failure.
+pub fn for_each_submodule<F, T>(mut action: F) -> anyhow::Result<Vec<T>>
+where
+    F: FnMut(&str) -> anyhow::Result<T>,
+{
+    let original_dir = current_dir()?;
+
+    let mut results = vec![];
+    for submodule in list_submodules()? {
+        let result = set_current_dir(original_dir.join(&submodule))
+            .with_context(|| format!("Failed to enter submodule - {:?}", submodule))
+            .and_then(|()| action(submodule.as_str()));
+
+        set_current_dir(&original_dir)?;
+        results.push(result?);
+    }
+
+    Ok(results)
+}
+
+/// Submodules which haven't been initialised have no config to check, so only checked out ones are listed
+fn list_submodules() -> anyhow::Result<Vec<String>> {
+    let output = Command::new("git")
+        .args([
+            "submodule",
+            "foreach",
+            "--quiet",
+            "--recursive",
+            "echo \"$displaypath\"",
+        ])
+        .output()?;
+
+    // Not a repository, which the caller will already be reporting
+    if !output.status.success() {
+        return Ok(vec![]);
+    }
+
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(|l| l.to_string())
+        .collect())
+}
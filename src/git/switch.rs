@@ -1,56 +1,147 @@
 use std::process::{Command, Stdio};
 
-use anyhow::{Context, anyhow};
+use anyhow::{anyhow, Context};
 
 use super::check;
 use crate::{
     config::LazyConfig,
-    git::{common::get_origin_url, GIT_SERVICE},
+    git::{
+        backend::GitBackend,
+        common::get_bool_config,
+        resolve::resolve_identity_and_url,
+        signing::{signing_config, SIGNING_CONFIG_KEYS},
+        ssh::{is_managed_ssh_command, ssh_command_for_key},
+    },
     identity::Identity,
 };
 
-pub fn prepare_switch(config: &mut LazyConfig) -> anyhow::Result<Identity<'_>> {
+/// The identity to switch to, with the URL of the remote it was matched by
+pub fn prepare_switch<'a>(
+    config: &'a mut LazyConfig,
+    backend: &dyn GitBackend,
+) -> anyhow::Result<(Identity<'a>, String)> {
     check::check_is_git_repository()
         .with_context(|| "Must be in a git repository to switch credentials")?;
 
-    let origin_url = get_origin_url()?;
+    resolve_identity_and_url(config, backend)
+}
 
-    config.account_for_url(GIT_SERVICE, origin_url.as_str())
+/// Which Git config file the identity is written to
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ConfigScope {
+    /// The repository's `.git/config`, shared by all of its worktrees
+    #[default]
+    Local,
+    /// Your `~/.gitconfig`, used by every repository which doesn't set its own values
+    Global,
+    /// Just the current worktree, which needs `extensions.worktreeConfig`
+    Worktree,
 }
 
-pub fn apply_switch(identity: Identity<'_>) -> anyhow::Result<()> {
+pub const CONFIG_SCOPES: [&str; 3] = ["local", "global", "worktree"];
+
+impl ConfigScope {
+    pub fn from_name(name: &str) -> anyhow::Result<ConfigScope> {
+        match name {
+            "local" => Ok(ConfigScope::Local),
+            "global" => Ok(ConfigScope::Global),
+            "worktree" => Ok(ConfigScope::Worktree),
+            _ => Err(anyhow!("Unknown config scope {}", name)),
+        }
+    }
+
+    fn flag(&self) -> &'static str {
+        match self {
+            ConfigScope::Local => "--local",
+            ConfigScope::Global => "--global",
+            ConfigScope::Worktree => "--worktree",
+        }
+    }
+}
+
+pub fn apply_switch(
+    backend: &dyn GitBackend,
+    identity: &Identity<'_>,
+    scope: ConfigScope,
+) -> anyhow::Result<()> {
     let username = identity.user().context("No username found")?;
     let email = identity.email().context("No email found")?;
 
-    set_username(username)?;
-    set_email(email)?;
+    // Git only reads config.worktree once the extension is turned on
+    if scope == ConfigScope::Worktree && !get_bool_config(backend, "extensions.worktreeConfig")? {
+        set_config(ConfigScope::Local, "extensions.worktreeConfig", "true")?;
+    }
+
+    set_config(scope, "user.name", username)?;
+    set_config(scope, "user.email", email)?;
+
+    match identity.ssh_key() {
+        Some(ssh_key) => set_config(
+            scope,
+            "core.sshCommand",
+            ssh_command_for_key(ssh_key).as_str(),
+        )?,
+        // Don't leave the previous identity's key in place, but leave anything else alone
+        None if is_managed_ssh_command(get_scoped_config(scope, "core.sshCommand")?.as_str()) => {
+            unset_config(scope, "core.sshCommand")?
+        }
+        None => {}
+    }
+
+    let signing = signing_config(identity);
+    if signing.is_empty() {
+        // Otherwise Git would keep signing with the previous identity's key
+        for key in SIGNING_CONFIG_KEYS {
+            if !get_scoped_config(scope, key)?.is_empty() {
+                unset_config(scope, key)?;
+            }
+        }
+    }
+
+    for (key, value) in signing {
+        set_config(scope, key, value.as_str())?;
+    }
 
     Ok(())
 }
 
-fn set_username(username: &str) -> anyhow::Result<()> {
+/// The value set in one scope, ignoring what the others set
+fn get_scoped_config(scope: ConfigScope, key: &str) -> anyhow::Result<String> {
+    Ok(String::from_utf8(
+        Command::new("git")
+            .args(["config", scope.flag(), key])
+            .output()?
+            .stdout,
+    )?
+    .trim_end()
+    .to_string())
+}
+
+fn set_config(scope: ConfigScope, key: &str, value: &str) -> anyhow::Result<()> {
     let code = Command::new("git")
-        .args(["config", "user.name", username])
+        .args(["config", scope.flag(), key, value])
         .stdout(Stdio::null())
         .stderr(Stdio::null())
-        .spawn()?.wait()?;
+        .spawn()?
+        .wait()?;
 
     if !code.success() {
-        return Err(anyhow!("Failed to set username"));
+        return Err(anyhow!("Failed to set {}", key));
     }
 
     Ok(())
 }
 
-fn set_email(email: &str) -> anyhow::Result<()> {
+fn unset_config(scope: ConfigScope, key: &str) -> anyhow::Result<()> {
     let code = Command::new("git")
-        .args(["config", "user.email", email])
+        .args(["config", scope.flag(), "--unset", key])
         .stdout(Stdio::null())
         .stderr(Stdio::null())
-        .spawn()?.wait()?;
+        .spawn()?
+        .wait()?;
 
     if !code.success() {
-        return Err(anyhow!("Failed to set email"));
+        return Err(anyhow!("Failed to unset {}", key));
     }
 
     Ok(())
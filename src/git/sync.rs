@@ -0,0 +1,229 @@
+use crate::config::{match_url_patterns, AccountConfig, LazyConfig};
+use crate::git::signing::signing_config;
+use crate::git::ssh::ssh_command_for_key;
+use crate::git::GIT_SERVICE;
+use crate::identity::Identity;
+use anyhow::{anyhow, Context};
+use std::collections::HashSet;
+use std::fs::{create_dir_all, read_dir, remove_file, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+const BLOCK_START: &str =
+    "# BEGIN identity managed block, changes will be overwritten by `identity git sync-global`";
+const BLOCK_END: &str = "# END identity managed block";
+
+const INCLUDE_HEADER: &str =
+    "# Managed by identity, changes will be overwritten by `identity git sync-global`";
+
+const INCLUDE_EXTENSION: &str = "gitconfig";
+
+pub fn run_git_sync_global(config: &mut LazyConfig) -> anyhow::Result<()> {
+    config.required()?;
+
+    let include_dir = get_include_dir()?;
+    let mut written_includes = HashSet::new();
+    // Later includes override earlier ones, so they're grouped to give the same precedence as resolving an identity does.
+    // The default comes first, then `match_path` rules, then `match_url` rules which are the most specific.
+    let mut default_includes = vec![];
+    let mut path_includes = vec![];
+    let mut url_includes = vec![];
+
+    for ic in &config.identity {
+        let accounts: Vec<&AccountConfig> = ic
+            .account
+            .iter()
+            .flatten()
+            .filter(|ac| ac.service == GIT_SERVICE)
+            .collect();
+
+        for (index, account) in accounts.iter().enumerate() {
+            let identity = Identity::from(ic, account);
+
+            let include_name = if accounts.len() == 1 {
+                format!("{}.{}", identity.id(), INCLUDE_EXTENSION)
+            } else {
+                format!("{}-{}.{}", identity.id(), index + 1, INCLUDE_EXTENSION)
+            };
+            let include_path = include_dir.join(&include_name);
+
+            write_include(&include_path, &identity)?;
+            written_includes.insert(include_name);
+
+            let include_path = quote_value(include_path.to_string_lossy().as_ref());
+
+            if account.default.or(ic.default).unwrap_or(false) {
+                default_includes.push(format!("[include]\n\tpath = {}", include_path));
+            }
+
+            if let Some(match_path) = identity.match_path() {
+                // A trailing slash makes Git match everything under the path, which includes the `.git` directory
+                let condition = format!("gitdir:{}/", match_path.trim_end_matches('/'));
+                path_includes.push(conditional_include(
+                    condition.as_str(),
+                    include_path.as_str(),
+                ));
+            }
+
+            if let Some(match_url) = identity.match_url() {
+                for pattern in match_url_patterns(match_url) {
+                    let condition = format!("hasconfig:remote.*.url:{}", pattern);
+                    url_includes.push(conditional_include(
+                        condition.as_str(),
+                        include_path.as_str(),
+                    ));
+                }
+            }
+        }
+    }
+
+    let mut block = vec![BLOCK_START.to_string()];
+    block.extend(default_includes);
+    block.extend(path_includes);
+    block.extend(url_includes);
+    block.push(BLOCK_END.to_string());
+
+    let removed = remove_stale_includes(&include_dir, &written_includes)?;
+
+    let gitconfig_path = get_gitconfig_path()?;
+    let mut content = String::new();
+    if gitconfig_path.exists() {
+        File::open(&gitconfig_path)
+            .with_context(|| format!("Failed to open Git config - {:?}", gitconfig_path))?
+            .read_to_string(&mut content)?;
+    }
+
+    let mut content = remove_managed_block(content.as_str());
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(block.join("\n").as_str());
+    content.push('\n');
+
+    File::create(&gitconfig_path)
+        .with_context(|| format!("Failed to open Git config - {:?}", gitconfig_path))?
+        .write_all(content.as_bytes())
+        .with_context(|| format!("Failed to write Git config - {:?}", gitconfig_path))?;
+
+    println!(
+        "Updated {:?} with {} include file(s), removed {} stale include file(s)",
+        gitconfig_path,
+        written_includes.len(),
+        removed
+    );
+
+    Ok(())
+}
+
+fn conditional_include(condition: &str, include_path: &str) -> String {
+    format!(
+        "[includeIf {}]\n\tpath = {}",
+        quote_value(condition),
+        include_path
+    )
+}
+
+fn write_include(include_path: &Path, identity: &Identity) -> anyhow::Result<()> {
+    let mut content = format!("{}\n[user]\n", INCLUDE_HEADER);
+    if let Some(user) = identity.user() {
+        content.push_str(format!("\tname = {}\n", quote_value(user)).as_str());
+    }
+    if let Some(email) = identity.email() {
+        content.push_str(format!("\temail = {}\n", quote_value(email)).as_str());
+    }
+    for (key, value) in signing_config(identity) {
+        let (section, name) = key.split_once('.').unwrap();
+        content.push_str(
+            format!(
+                "[{}]\n\t{} = {}\n",
+                section,
+                name,
+                quote_value(value.as_str())
+            )
+            .as_str(),
+        );
+    }
+    if let Some(ssh_key) = identity.ssh_key() {
+        content.push_str(
+            format!(
+                "[core]\n\tsshCommand = {}\n",
+                quote_value(ssh_command_for_key(ssh_key).as_str())
+            )
+            .as_str(),
+        );
+    }
+
+    File::create(include_path)
+        .with_context(|| format!("Failed to create include file - {:?}", include_path))?
+        .write_all(content.as_bytes())
+        .with_context(|| format!("Failed to write include file - {:?}", include_path))?;
+
+    Ok(())
+}
+
+fn remove_stale_includes(include_dir: &Path, keep: &HashSet<String>) -> anyhow::Result<usize> {
+    let mut removed = 0;
+
+    for entry in read_dir(include_dir)? {
+        let path = entry?.path();
+        let is_include = path
+            .extension()
+            .map(|e| e == INCLUDE_EXTENSION)
+            .unwrap_or(false);
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string());
+
+        if is_include && !name.map(|n| keep.contains(&n)).unwrap_or(true) {
+            remove_file(&path)
+                .with_context(|| format!("Failed to remove stale include file - {:?}", path))?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+fn remove_managed_block(content: &str) -> String {
+    let mut in_block = false;
+    let mut lines = vec![];
+
+    for line in content.lines() {
+        if line == BLOCK_START {
+            in_block = true;
+        } else if line == BLOCK_END && in_block {
+            in_block = false;
+        } else if !in_block {
+            lines.push(line);
+        }
+    }
+
+    let mut content = lines.join("\n");
+    if !content.is_empty() {
+        content.push('\n');
+    }
+
+    content
+}
+
+fn quote_value(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn get_include_dir() -> anyhow::Result<PathBuf> {
+    let home_path = match home::home_dir() {
+        Some(p) => p,
+        None => return Err(anyhow!("Unable to find your home directory")),
+    };
+
+    let include_dir = home_path.join(".config").join("identity").join("git");
+    create_dir_all(&include_dir)
+        .with_context(|| format!("Failed to create include directory - {:?}", include_dir))?;
+
+    Ok(include_dir)
+}
+
+fn get_gitconfig_path() -> anyhow::Result<PathBuf> {
+    match home::home_dir() {
+        Some(p) => Ok(p.join(".gitconfig")),
+        None => Err(anyhow!("Unable to find your home directory")),
+    }
+}
@@ -1,7 +1,29 @@
-use crate::git::common::{get_email, get_username};
+use crate::git::backend::get_backend;
+use crate::git::common::{describe_config_origin, get_email, get_ssh_command, get_username};
 
 pub fn run_who_am_i() -> anyhow::Result<()> {
-    println!("user.name  = {}", get_username()?);
-    println!("user.email = {}", get_email()?);
+    let backend = get_backend();
+    let backend = backend.as_ref();
+
+    println!(
+        "user.name  = {}{}",
+        get_username(backend)?,
+        describe_config_origin(backend, "user.name")?
+    );
+    println!(
+        "user.email = {}{}",
+        get_email(backend)?,
+        describe_config_origin(backend, "user.email")?
+    );
+
+    let ssh_command = get_ssh_command(backend)?;
+    if !ssh_command.is_empty() {
+        println!(
+            "core.sshCommand = {}{}",
+            ssh_command,
+            describe_config_origin(backend, "core.sshCommand")?
+        );
+    }
+
     Ok(())
 }
@@ -1,10 +1,11 @@
-use crate::config::{AccountConfig, IdentityConfig};
+use crate::config::{AccountConfig, IdentityConfig, SigningFormat};
 use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Clone)]
 pub struct Identity<'a> {
     identity_config: &'a IdentityConfig,
     account_config: &'a AccountConfig,
+    default: bool,
 }
 
 impl<'a> Identity<'a> {
@@ -12,9 +13,20 @@ impl<'a> Identity<'a> {
         Identity {
             identity_config,
             account_config,
+            default: false,
         }
     }
 
+    /// Mark this identity as having been chosen because it is the default, rather than because it matched.
+    pub fn into_default(mut self) -> Self {
+        self.default = true;
+        self
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.default
+    }
+
     pub fn id(&self) -> &str {
         self.identity_config.id.as_str()
     }
@@ -34,6 +46,10 @@ impl<'a> Identity<'a> {
         self.account_config.match_url.as_ref()
     }
 
+    pub fn match_path(&self) -> Option<&String> {
+        self.account_config.match_path.as_ref()
+    }
+
     pub fn description(&self) -> Option<&String> {
         self.account_config
             .description
@@ -44,6 +60,18 @@ impl<'a> Identity<'a> {
     pub fn token(&self) -> Option<&String> {
         self.account_config.token.as_ref()
     }
+
+    pub fn ssh_key(&self) -> Option<&String> {
+        self.account_config.ssh_key.as_ref()
+    }
+
+    pub fn signing_key(&self) -> Option<&String> {
+        self.account_config.signing_key.as_ref()
+    }
+
+    pub fn signing_format(&self) -> Option<SigningFormat> {
+        self.account_config.signing_format
+    }
 }
 
 impl<'a> Display for Identity<'a> {
@@ -1,15 +1,14 @@
-use crate::cargo::CARGO_SERVICE;
 use crate::config::{IdentityConfig, LazyConfig};
-use crate::git::GIT_SERVICE;
+use crate::service::SERVICES;
 use anyhow::anyhow;
 use clap::ArgMatches;
-use inquire::{Select, Confirm};
+use inquire::{Confirm, Select};
 
 pub fn get_or_prompt_for_service(arg_matches: &ArgMatches) -> anyhow::Result<String> {
     let service = arg_matches
         .get_one::<String>("service")
         .cloned()
-        .or_else(|| prompt_for_service().map_or_else(|_| None, Some));
+        .or_else(|| prompt_for_service().ok());
 
     if let Some(s) = service {
         Ok(s)
@@ -19,7 +18,7 @@ pub fn get_or_prompt_for_service(arg_matches: &ArgMatches) -> anyhow::Result<Str
 }
 
 fn prompt_for_service() -> anyhow::Result<String> {
-    let selector = Select::new("Select service", vec![GIT_SERVICE, CARGO_SERVICE]);
+    let selector = Select::new("Select service", SERVICES.to_vec());
     Ok(selector.prompt()?.to_string())
 }
 
@@ -38,7 +37,7 @@ pub fn get_or_prompt_for_target_identity(
             identity_configs.first().cloned().cloned()
         })
         .unwrap_or(None)
-        .or_else(|| prompt_for_target_identity(config).map_or_else(|_| None, Some));
+        .or_else(|| prompt_for_target_identity(config).ok());
 
     if let Some(t) = target_identity {
         Ok(t)
@@ -1,7 +1,10 @@
 #![doc = include_str!("../README.md")]
 
+use crate::completions::run_completions;
 use crate::config::{verify_config, LazyConfig};
 use crate::git::run_git;
+use crate::prompt::run_prompt;
+use crate::shell::run_shell_init;
 use crate::switch::run_switch;
 use crate::whoami::run_who_am_i;
 
@@ -9,9 +12,13 @@ mod cargo;
 mod git;
 
 mod cli;
+mod completions;
 mod config;
 mod identity;
 mod input;
+mod prompt;
+mod service;
+mod shell;
 mod switch;
 mod whoami;
 
@@ -26,7 +33,10 @@ fn main() -> anyhow::Result<()> {
     }
 
     match matches.subcommand() {
+        Some(("completions", sub_matches)) => run_completions(&mut config, sub_matches),
         Some(("git", sub_matches)) => run_git(&mut config, sub_matches),
+        Some(("prompt", sub_matches)) => run_prompt(sub_matches),
+        Some(("shell-init", sub_matches)) => run_shell_init(sub_matches),
         Some(("switch", sub_matches)) => run_switch(&mut config, sub_matches),
         Some(("whoami", sub_matches)) => run_who_am_i(&mut config, sub_matches),
         _ => {
@@ -0,0 +1,250 @@
+use crate::config::{get_config_path, LazyConfig};
+use crate::git;
+use anyhow::anyhow;
+use clap::{arg, value_parser, ArgAction, ArgMatches, Command};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs::{create_dir_all, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct PromptCache {
+    repository: HashMap<String, PromptCacheEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct PromptCacheEntry {
+    /// When each file the token depends on was last modified, zero for files which don't exist yet
+    modified: BTreeMap<String, u64>,
+    /// Lookups which timed out are retried after this time, rather than making every prompt wait for them
+    expires: Option<u64>,
+    token: String,
+}
+
+/// How long to show nothing for a repository whose identity took too long to work out
+const TIMED_OUT_RETRY: Duration = Duration::from_secs(30);
+
+pub fn configure_command() -> Command {
+    Command::new("prompt")
+        .about("Print the Git identity for the current repository, for use in a shell prompt")
+        .arg(
+            arg!(--color "Colour the output, green for a match and red for a mismatch")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--timeout "Maximum time to spend working out the identity, in milliseconds")
+                .action(ArgAction::Set)
+                .value_name("MS")
+                .value_parser(value_parser!(u64))
+                .default_value("200"),
+        )
+}
+
+pub fn run_prompt(arg_matches: &ArgMatches) -> anyhow::Result<()> {
+    // The prompt is drawn all the time, so problems are left for `identity git --check` to report
+    let token = match get_token(arg_matches) {
+        Ok(Some(token)) if !token.is_empty() => token,
+        _ => return Ok(()),
+    };
+
+    if arg_matches.get_flag("color") {
+        let colour = if token.starts_with('!') { 31 } else { 32 };
+        println!("\x1b[{}m{}\x1b[0m", colour, token);
+    } else {
+        println!("{}", token);
+    }
+
+    Ok(())
+}
+
+fn get_token(arg_matches: &ArgMatches) -> anyhow::Result<Option<String>> {
+    let repository = match find_repository()? {
+        Some(r) => r,
+        None => return Ok(None),
+    };
+
+    if let Some(token) = get_cached_token(&repository)? {
+        return Ok(Some(token));
+    }
+
+    let timeout = Duration::from_millis(*arg_matches.get_one::<u64>("timeout").unwrap());
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut config = LazyConfig::new();
+        let backend = git::get_backend();
+        let _ = sender.send(
+            git::get_prompt_token(&mut config, backend.as_ref())
+                .and_then(|token| Ok((token, backend.get_config_files()?))),
+        );
+    });
+
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => {
+            let (token, git_config_files) = result?;
+            update_cached_token(&repository, token.as_str(), &git_config_files, None)?;
+            Ok(Some(token))
+        }
+        // Better to show nothing than to hold up the prompt
+        Err(_) => {
+            let expires = now_millis()? + TIMED_OUT_RETRY.as_millis() as u64;
+            update_cached_token(&repository, "", &[], Some(expires))?;
+            Ok(None)
+        }
+    }
+}
+
+/// Find the repository root by looking for `.git` rather than asking Git, which is too slow for every prompt.
+fn find_repository() -> anyhow::Result<Option<PathBuf>> {
+    let current_dir = std::env::current_dir()?;
+
+    Ok(current_dir
+        .ancestors()
+        .find(|p| p.join(".git").exists())
+        .map(Path::to_path_buf))
+}
+
+fn get_cached_token(repository: &Path) -> anyhow::Result<Option<String>> {
+    let entry = match load_cache()?
+        .repository
+        .remove(repository.to_string_lossy().as_ref())
+    {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+
+    if entry
+        .expires
+        .map_or(false, |expires| expires < now_millis().unwrap_or(u64::MAX))
+    {
+        return Ok(None);
+    }
+
+    // Files created since, like a new `~/.gitconfig`, were recorded as zero so they show up as modified too
+    for (path, modified) in &entry.modified {
+        if modified_millis(Path::new(path)) != *modified {
+            return Ok(None);
+        }
+    }
+
+    Ok(Some(entry.token))
+}
+
+fn update_cached_token(
+    repository: &Path,
+    token: &str,
+    git_config_files: &[String],
+    expires: Option<u64>,
+) -> anyhow::Result<()> {
+    let mut modified = BTreeMap::new();
+    let files = watched_files(repository)?
+        .into_iter()
+        .chain(git_config_files.iter().map(PathBuf::from));
+    for path in files {
+        modified.insert(path.to_string_lossy().to_string(), modified_millis(&path));
+    }
+
+    let mut cache = load_cache()?;
+    cache.repository.insert(
+        repository.to_string_lossy().to_string(),
+        PromptCacheEntry {
+            modified,
+            expires,
+            token: token.to_string(),
+        },
+    );
+
+    let mut f = File::create(get_cache_path()?)?;
+    f.write_all(toml::to_string(&cache)?.as_bytes())?;
+
+    Ok(())
+}
+
+/// The files which are worth checking without asking Git, the rest come from the Git backend when the token is built
+fn watched_files(repository: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = vec![get_config_path()?];
+
+    match env::var_os("GIT_CONFIG_GLOBAL") {
+        Some(global_config) => files.push(PathBuf::from(global_config)),
+        None => {
+            if let Some(home_path) = home::home_dir() {
+                let xdg_config = env::var_os("XDG_CONFIG_HOME")
+                    .filter(|x| !x.is_empty())
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| home_path.join(".config"));
+                files.push(xdg_config.join("git").join("config"));
+                files.push(home_path.join(".gitconfig"));
+            }
+        }
+    }
+
+    // For a worktree or submodule `.git` is a file, the Git backend reports where its config really is
+    let git_path = repository.join(".git");
+    if git_path.is_dir() {
+        files.push(git_path.join("config"));
+        files.push(git_path.join("config.worktree"));
+        files.push(git_path.join("HEAD"));
+    } else {
+        files.push(git_path);
+        files.extend(get_head_path(repository));
+    }
+
+    Ok(files)
+}
+
+/// Checking out another branch can change which remote, and so which identity, applies.
+/// Worktrees and submodules keep HEAD elsewhere, asking Git is fine as this is only needed when the cache is updated.
+fn get_head_path(repository: &Path) -> Option<PathBuf> {
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "--git-path", "HEAD"])
+        .current_dir(repository)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+
+    let path = String::from_utf8(output.stdout).ok()?;
+    Some(repository.join(path.trim_end()))
+}
+
+fn modified_millis(path: &Path) -> u64 {
+    path.metadata()
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn now_millis() -> anyhow::Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64)
+}
+
+fn load_cache() -> anyhow::Result<PromptCache> {
+    let cache_path = get_cache_path()?;
+    if !cache_path.exists() {
+        return Ok(PromptCache::default());
+    }
+
+    let mut content = String::new();
+    File::open(&cache_path)?.read_to_string(&mut content)?;
+
+    // The cache can always be rebuilt, so don't fail the prompt because of it
+    Ok(toml::from_str(&content).unwrap_or_default())
+}
+
+fn get_cache_path() -> anyhow::Result<PathBuf> {
+    let home_path = match home::home_dir() {
+        Some(p) => p,
+        None => return Err(anyhow!("Unable to find your home directory")),
+    };
+
+    let cache_dir = home_path.join(".cache").join("identity");
+    create_dir_all(&cache_dir)?;
+
+    Ok(cache_dir.join("prompt.toml"))
+}
@@ -0,0 +1,5 @@
+use crate::cargo::CARGO_SERVICE;
+use crate::git::GIT_SERVICE;
+
+/// Every service that identities can have accounts for
+pub const SERVICES: [&str; 2] = [GIT_SERVICE, CARGO_SERVICE];
@@ -0,0 +1,64 @@
+use clap::{arg, ArgAction, ArgMatches, Command};
+
+const BASH_HOOK: &str = r#"__identity_hook() {
+  [[ "$PWD" == "${__identity_pwd:-}" ]] && return
+  __identity_pwd="$PWD"
+  if [[ -n "${__identity_repo:-}" && ( "$PWD" == "$__identity_repo" || "$PWD" == "$__identity_repo/"* ) ]]; then
+    return
+  fi
+  __identity_repo="$(command identity git hook --chpwd{args})"
+}
+if [[ ";${PROMPT_COMMAND:-};" != *";__identity_hook;"* ]]; then
+  PROMPT_COMMAND="__identity_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+fi
+"#;
+
+const ZSH_HOOK: &str = r#"__identity_hook() {
+  if [[ -n "${__identity_repo:-}" && ( "$PWD" == "$__identity_repo" || "$PWD" == "$__identity_repo/"* ) ]]; then
+    return
+  fi
+  __identity_repo="$(command identity git hook --chpwd{args})"
+}
+autoload -Uz add-zsh-hook
+add-zsh-hook chpwd __identity_hook
+__identity_hook
+"#;
+
+const FISH_HOOK: &str = r#"function __identity_hook --on-variable PWD
+    if test -n "$__identity_repo"; and begin; test "$PWD" = "$__identity_repo"; or string match -q -- "$__identity_repo/*" "$PWD"; end
+        return
+    end
+    set -g __identity_repo (command identity git hook --chpwd{args})
+end
+__identity_hook
+"#;
+
+pub fn configure_command() -> Command {
+    Command::new("shell-init")
+        .about("Print a shell hook which checks your Git identity when entering a repository")
+        .arg(arg!(<SHELL> "The shell to print the hook for").value_parser(["bash", "zsh", "fish"]))
+        .arg(
+            arg!(--apply "Apply the matched identity instead of printing a warning")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+pub fn run_shell_init(arg_matches: &ArgMatches) -> anyhow::Result<()> {
+    let shell = arg_matches.get_one::<String>("SHELL").unwrap();
+
+    let hook = match shell.as_str() {
+        "bash" => BASH_HOOK,
+        "zsh" => ZSH_HOOK,
+        _ => FISH_HOOK,
+    };
+
+    let args = if arg_matches.get_flag("apply") {
+        " --apply"
+    } else {
+        ""
+    };
+
+    print!("{}", hook.replace("{args}", args));
+
+    Ok(())
+}
@@ -4,7 +4,7 @@ use crate::{cargo, git};
 use anyhow::{anyhow, Context};
 use cargo::CARGO_SERVICE;
 use clap::{arg, ArgAction, ArgMatches, Command};
-use git::GIT_SERVICE;
+use git::{ConfigScope, CONFIG_SCOPES, GIT_SERVICE};
 
 pub fn configure_command() -> Command {
     Command::new("switch")
@@ -21,6 +21,16 @@ pub fn configure_command() -> Command {
                 .value_name("ID")
                 .num_args(1),
         )
+        .arg(
+            arg!(--scope "Which Git config to write the identity to, defaults to the repository's own")
+                .action(ArgAction::Set)
+                .value_name("SCOPE")
+                .value_parser(CONFIG_SCOPES),
+        )
+        .arg(
+            arg!(--"recurse-submodules" "Also switch each submodule to the identity matched for its own remote")
+                .action(ArgAction::SetTrue),
+        )
 }
 
 pub fn run_switch(config: &mut LazyConfig, arg_matches: &ArgMatches) -> anyhow::Result<()> {
@@ -28,16 +38,63 @@ pub fn run_switch(config: &mut LazyConfig, arg_matches: &ArgMatches) -> anyhow::
 
     let service = get_or_prompt_for_service(arg_matches)?;
 
+    let scope = arg_matches
+        .get_one::<String>("scope")
+        .map(|s| ConfigScope::from_name(s))
+        .transpose()?;
+    if scope.is_some() && service != GIT_SERVICE {
+        return Err(anyhow!(
+            "`--scope` is only supported for the {} service",
+            GIT_SERVICE
+        ));
+    }
+
+    let recurse_submodules = arg_matches.get_flag("recurse-submodules");
+    if recurse_submodules && service != GIT_SERVICE {
+        return Err(anyhow!(
+            "`--recurse-submodules` is only supported for the {} service",
+            GIT_SERVICE
+        ));
+    }
+    // Every submodule would write over the same global values
+    if recurse_submodules && scope == Some(ConfigScope::Global) {
+        return Err(anyhow!(
+            "`--recurse-submodules` can't be used with `--scope global`"
+        ));
+    }
+
     match service.as_str() {
         GIT_SERVICE => {
-            let matched_identity = git::prepare_switch(config).context("Could not find an identity to switch to based on the origin of this git repository")?;
+            let backend = git::get_backend();
+            let (matched_identity, remote_url) = git::prepare_switch(config, backend.as_ref()).context("Could not find an identity to switch to based on the origin of this git repository")?;
 
-            let confirm = prompt_confirm(format!("Selected identity `{}` based on the git origin, apply? (y/n)", matched_identity.id()).as_str())?;
+            let confirm = prompt_confirm(
+                format!(
+                    "Selected identity `{}` based on the git origin, apply? (y/n)",
+                    matched_identity.id()
+                )
+                .as_str(),
+            )?;
             if confirm {
-                git::apply_switch(matched_identity)?;
+                git::apply_switch(
+                    backend.as_ref(),
+                    &matched_identity,
+                    scope.unwrap_or_default(),
+                )?;
+                if git::sync_credentials(backend.as_ref(), &matched_identity, remote_url.as_str())?
+                {
+                    println!(
+                        "Stored the token for `{}` in your Git credentials helper",
+                        matched_identity.id()
+                    );
+                }
                 println!("Applied successfully, running `whoami` to verify");
 
                 git::run_who_am_i()?;
+
+                if recurse_submodules {
+                    git::run_git_switch_submodules(config, scope.unwrap_or_default())?;
+                }
             } else {
                 println!("Okay, stopping without making changes");
             }
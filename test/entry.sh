@@ -7,5 +7,6 @@ echo -e "\033[34;40;3mStarting tests\033[0m"
 ./test_verify.sh
 ./test_git.sh
 ./test_cargo.sh
+./test_shell.sh
 
 echo -e "\033[34;40;3mFinished testing\033[0m"
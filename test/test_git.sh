@@ -56,6 +56,36 @@ user = "bad-credentials"
 match_url = "https://github.com/bad-credentials/*"
 token = "the-wrong-token"
 
+[[identity]]
+id = "worker"
+email = "worker@example.com"
+
+[[identity.account]]
+service = "git"
+user = "worker"
+match_path = "~/work/*"
+
+[[identity]]
+id = "keyholder"
+email = "keyholder@example.com"
+
+[[identity.account]]
+service = "git"
+user = "keyholder"
+match_url = "git@github.com:keyholder/*"
+ssh_key = "~/.ssh/keyholder"
+
+[[identity]]
+id = "signer"
+email = "signer@example.com"
+
+[[identity.account]]
+service = "git"
+user = "signer"
+match_url = "https://github.com/signer/*"
+signing_key = "0123456789ABCDEF"
+signing_format = "gpg"
+
 END
 
 git config --global init.defaultBranch main
@@ -133,22 +163,48 @@ if [[ $content_check_result -ne 0 ]]; then
   exit 1
 fi
 
-echo -e "\033[34;40;3m- Will not overwrite existing pre-commit hook\033[0m"
+echo -e "\033[34;40;3m- Add identity check to existing pre-commit hook\033[0m"
 set +e
 identity git install &> output.txt
 check_result=$?
-grep -qe "Pre-commit hook already exists, run with --force to overwrite" output.txt
+grep -qe "Identity check added to existing hook at" output.txt
 content_check_result=$?
 set -e
 
-if [[ $check_result -ne 1 ]]; then
+if [[ $check_result -ne 0 ]]; then
   cat output.txt
-  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
   exit 1
 fi
 
-if [[ $content_check_result -ne 0 ]]; then
+if [[ $content_check_result -ne 0 ]] || ! head -1 .git/hooks/pre-commit | grep -qe "#!/usr/local/env bash"; then
+  cat output.txt .git/hooks/pre-commit
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+echo -e "\033[34;40;3m- Install is idempotent\033[0m"
+identity git install > /dev/null
+if [[ $(grep -ce "identity git hook --pre-commit" .git/hooks/pre-commit) -ne 1 ]]; then
+  cat .git/hooks/pre-commit
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+echo -e "\033[34;40;3m- Uninstall only removes the identity check\033[0m"
+set +e
+identity git uninstall &> output.txt
+check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
   cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if grep -qe "identity" .git/hooks/pre-commit || ! grep -qe "#!/usr/local/env bash" .git/hooks/pre-commit; then
+  cat .git/hooks/pre-commit
   echo -e "\033[91;40mWrong content\033[0m"
   exit 1
 fi
@@ -370,3 +426,1491 @@ if [[ $content_check_result -ne 0 ]]; then
   echo -e "\033[91;40mWrong content\033[0m"
   exit 1
 fi
+
+sed -i 's/the-right-token/the-new-token/' ~/.config/identity.toml
+
+echo -e "\033[34;40;3m- Store the configured token in the credentials helper\033[0m"
+set +e
+identity git sync-credentials &> output.txt
+check_result=$?
+identity git --check &>> output.txt
+grep -qe "Everything looks good!" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]] || ! grep -qe "Stored the token for \`bad-credentials\`" output.txt; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+sed -i 's/the-new-token/the-right-token/' ~/.config/identity.toml
+identity git sync-credentials > /dev/null
+
+cp ~/.config/identity.toml identity.toml.bak
+cat >> ~/.config/identity.toml << END
+
+[[identity]]
+id = "ssh-token"
+email = "ssh-token@example.com"
+
+[[identity.account]]
+service = "git"
+user = "ssh-token"
+match_url = "git@github.com:ssh-token/*"
+token = "an-ssh-token"
+END
+git remote set-url origin git@github.com:ssh-token/credentials_project.git
+
+echo -e "\033[34;40;3m- Skip the credentials helper for SSH remotes\033[0m"
+set +e
+identity git sync-credentials &> output.txt
+check_result=$?
+grep -qe "The remote \`git@github.com:ssh-token/credentials_project.git\` doesn't use HTTP" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]] || printf 'protocol=https\nhost=github.com\nusername=ssh-token\n\n' | git credential fill 2>/dev/null | grep -qe "an-ssh-token"; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git remote set-url origin https://github.com/bad-credentials/credentials_project.git
+mv identity.toml.bak ~/.config/identity.toml
+
+git remote rename origin upstream
+git remote add origin https://github.com/someone-else/credentials_project.git
+git config credential.useHttpPath true
+sed -i 's/the-right-token/the-fork-token/' ~/.config/identity.toml
+
+echo -e "\033[34;40;3m- Sync credentials for the remote the identity matched in a fork\033[0m"
+set +e
+identity git sync-credentials &> output.txt
+check_result=$?
+printf 'url=https://github.com/bad-credentials/credentials_project.git\nusername=bad-credentials\n\n' | GIT_TERMINAL_PROMPT=0 git credential fill 2>/dev/null | grep -qe "password=the-fork-token"
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git remote remove origin
+
+echo -e "\033[34;40;3m- Sync credentials without an origin\033[0m"
+set +e
+identity git sync-credentials &> output.txt
+check_result=$?
+grep -qe "Stored the token for \`bad-credentials\`" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git remote rename upstream origin
+git config --unset credential.useHttpPath
+sed -i 's/the-fork-token/the-right-token/' ~/.config/identity.toml
+identity git sync-credentials > /dev/null
+
+mkdir -p ~/work
+cd ~/work
+git init --quiet path_project && cd path_project || exit
+git config user.name "worker"
+git config user.email "worker@example.com"
+identity git install > /dev/null
+
+echo -e "\033[34;40;3m- Match identity by path without a remote\033[0m"
+set +e
+identity git --check &> output.txt
+check_result=$?
+grep -qe "Everything looks good!" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+cat >> ~/.config/identity.toml << END
+
+[[identity]]
+id = "fallback"
+email = "fallback@example.com"
+default = true
+
+[[identity.account]]
+service = "git"
+user = "fallback"
+END
+
+cd "$(mktemp -d)"
+git init --quiet unmatched_project && cd unmatched_project || exit
+git config user.name "fallback"
+git config user.email "fallback@example.com"
+git remote add origin https://github.com/nobody/project.git
+
+echo -e "\033[34;40;3m- Use the default identity when nothing matches\033[0m"
+set +e
+identity git hook --pre-commit &> output.txt
+check_result=$?
+grep -qe "using default identity \`fallback\`" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+sed -i '1a strict = true' ~/.config/identity.toml
+
+echo -e "\033[34;40;3m- Strict mode ignores the default identity\033[0m"
+set +e
+identity git hook --pre-commit &> output.txt
+check_result=$?
+grep -qe "No identity found for URL" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+sed -i '/^strict = true$/d' ~/.config/identity.toml
+
+mkdir -p ~/work/sync_project
+cd ~/work/sync_project
+git init --quiet
+
+echo -e "\033[34;40;3m- Generate global conditional includes\033[0m"
+set +e
+identity git sync-global &> output.txt
+check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ "$(git config user.email)" != "worker@example.com" ]]; then
+  cat output.txt
+  cat ~/.gitconfig
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git init --quiet nested_project
+# Without a glob, Git's `gitdir:` still matches everything below the path
+sed -i 's|^match_path = "~/work/\*"$|match_path = "~/work"|' ~/.config/identity.toml
+identity git sync-global > /dev/null
+
+echo -e "\033[34;40;3m- Conditional includes match nested repositories like identity does\033[0m"
+set +e
+(cd nested_project && identity prompt) &> output.txt
+check_result=$?
+grep -qxe "worker" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 || "$(git -C nested_project config user.email)" != "worker@example.com" ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+rm -rf nested_project
+sed -i 's|^match_path = "~/work"$|match_path = "~/work/*"|' ~/.config/identity.toml
+identity git sync-global > /dev/null
+git remote add origin https://github.com/tester/sync_project.git
+ssh_sync_project="$(mktemp -d)/ssh_sync_project"
+git init --quiet "$ssh_sync_project"
+git -C "$ssh_sync_project" remote add origin git@github.com:keyholder/sync_project.git
+
+echo -e "\033[34;40;3m- Conditional includes match URLs like identity does\033[0m"
+set +e
+identity whoami --service git > /dev/null
+check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ "$(git config user.email)" != "tester@example.com" || "$(identity prompt)" != "tester" ]] \
+  || [[ "$(git -C "$ssh_sync_project" config user.email)" != "keyholder@example.com" ]]; then
+  cat ~/.gitconfig
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git remote remove origin
+
+cp ~/.config/identity.toml identity.toml.bak
+sed -i '/^\[\[identity\]\]$/,$d' ~/.config/identity.toml
+echo 'identity = []' >> ~/.config/identity.toml
+identity git sync-global > /dev/null
+mv identity.toml.bak ~/.config/identity.toml
+
+echo -e "\033[34;40;3m- Remove stale conditional includes\033[0m"
+if grep -qe "includeIf" ~/.gitconfig || [[ -n "$(ls ~/.config/identity/git/)" ]]; then
+  cat ~/.gitconfig
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+cd "$(mktemp -d)"
+git init --quiet ssh_project && cd ssh_project || exit
+git config user.name "keyholder"
+git config user.email "keyholder@example.com"
+git remote add origin git@github.com:keyholder/project.git
+identity git install > /dev/null
+
+mkdir -p ~/.ssh
+touch ~/.ssh/keyholder
+chmod 644 ~/.ssh/keyholder
+
+echo -e "\033[34;40;3m- Reject SSH key readable by others\033[0m"
+set +e
+identity git --check &> output.txt
+check_result=$?
+grep -qe "SSH key is accessible by other users" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+chmod 600 ~/.ssh/keyholder
+
+echo -e "\033[34;40;3m- Reject missing SSH command\033[0m"
+set +e
+identity git --check &> output.txt
+check_result=$?
+grep -qe "SSH command mismatch" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git config core.sshCommand "ssh -i $HOME/.ssh/keyholder -o IdentitiesOnly=yes"
+
+echo -e "\033[34;40;3m- Accepts the configured SSH key\033[0m"
+set +e
+identity git --check &> output.txt
+check_result=$?
+grep -qe "Everything looks good!" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+cd ..
+git init --quiet signing_project && cd signing_project || exit
+git config user.name "signer"
+git config user.email "signer@example.com"
+git remote add origin https://github.com/signer/project.git
+identity git install > /dev/null
+
+echo -e "\033[34;40;3m- Reject missing signing key\033[0m"
+set +e
+identity git --check &> output.txt
+check_result=$?
+grep -qe "Signing config mismatch for user.signingkey - expected=0123456789ABCDEF != actual=" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git config user.signingkey "0123456789ABCDEF"
+git config commit.gpgsign true
+
+echo -e "\033[34;40;3m- Accepts the configured signing key\033[0m"
+set +e
+identity git --check &> output.txt
+check_result=$?
+grep -qe "Everything looks good!" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git config gpg.format openpgp
+git remote set-url origin https://github.com/tester/project.git
+
+echo -e "\033[34;40;3m- Switching to an identity without a signing key stops signing\033[0m"
+set +e
+identity git hook --chpwd --apply &> output.txt
+check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ "$(git config user.email)" != "tester@example.com" ]] || git config user.signingkey || git config gpg.format \
+  || git config commit.gpgsign; then
+  cat output.txt
+  cat .git/config
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+cd ..
+git init --quiet chain_project && cd chain_project || exit
+git config user.name "tester"
+git config user.email "tester@example.com"
+git remote add origin https://github.com/tester/chain_project.git
+
+echo -e '#!/usr/bin/env perl\nprint "chained\\n";' > .git/hooks/pre-commit
+chmod +x .git/hooks/pre-commit
+
+echo -e "\033[34;40;3m- Chain to an existing hook which is not a shell script\033[0m"
+set +e
+identity git install &> output.txt
+check_result=$?
+git commit --quiet --allow-empty -m "chained" &> commit_output.txt
+commit_result=$?
+set -e
+
+if [[ $check_result -ne 0 || $commit_result -ne 0 ]]; then
+  cat output.txt commit_output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result and $commit_result\033[0m"
+  exit 1
+fi
+
+if ! grep -qe "chained" commit_output.txt; then
+  cat output.txt commit_output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+identity git uninstall > /dev/null
+
+echo -e "\033[34;40;3m- Uninstall restores the chained hook\033[0m"
+if ! grep -qe "print \"chained" .git/hooks/pre-commit || [[ -e .git/hooks/pre-commit.identity-chained ]]; then
+  cat .git/hooks/pre-commit
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+identity git install > /dev/null
+
+echo -e "\033[34;40;3m- Force install replaces the chained hook\033[0m"
+set +e
+identity git install --force &> output.txt
+check_result=$?
+git commit --quiet --allow-empty -m "not chained" &> commit_output.txt
+commit_result=$?
+set -e
+
+if [[ $check_result -ne 0 || $commit_result -ne 0 ]]; then
+  cat output.txt commit_output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result and $commit_result\033[0m"
+  exit 1
+fi
+
+if grep -qe "chained" commit_output.txt || [[ -e .git/hooks/pre-commit.identity-chained ]]; then
+  cat output.txt commit_output.txt .git/hooks/pre-commit
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+echo -e '#!/usr/bin/env bash\nidentity git hook --pre-commit' > .git/hooks/pre-commit
+
+echo -e "\033[34;40;3m- Uninstall removes a hook from an older version\033[0m"
+set +e
+identity git uninstall &> output.txt
+check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ -e .git/hooks/pre-commit ]]; then
+  cat output.txt .git/hooks/pre-commit
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+cd ..
+git init --quiet hooks_path_project && cd hooks_path_project || exit
+git config user.name "tester"
+git config user.email "tester@example.com"
+git config core.hooksPath .githooks
+git remote add origin https://github.com/tester/hooks_path_project.git
+
+echo -e "\033[34;40;3m- Install respects core.hooksPath\033[0m"
+set +e
+identity git install &> output.txt
+identity git --check &>> output.txt
+check_result=$?
+grep -qe "Everything looks good!" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 || ! -x .githooks/pre-commit ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+cd ..
+git init --quiet worktree_project && cd worktree_project || exit
+git config user.name "tester"
+git config user.email "tester@example.com"
+git remote add origin https://github.com/tester/worktree_project.git
+git commit --quiet --allow-empty -m "initial commit"
+git worktree add --quiet ../linked_worktree
+cd ../linked_worktree
+
+echo -e "\033[34;40;3m- Install and check from a linked worktree\033[0m"
+set +e
+identity git install &> output.txt
+identity git --check &>> output.txt
+check_result=$?
+grep -qe "Everything looks good!" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 || ! -x ../worktree_project/.git/hooks/pre-commit ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+# Git itself has to work to run the hook, so only `identity` is stopped from running it
+mkdir -p ~/broken_git ~/identity_without_git
+printf '#!/bin/sh\nexit 1\n' > ~/broken_git/git
+chmod +x ~/broken_git/git
+printf '#!/bin/sh\nPATH="$HOME/broken_git:$PATH" exec %s "$@"\n' "$(command -v identity)" > ~/identity_without_git/identity
+chmod +x ~/identity_without_git/identity
+
+echo -e "\033[34;40;3m- Run the hook from a linked worktree without running Git\033[0m"
+set +e
+PATH="$HOME/identity_without_git:$PATH" git -c user.email="leaked@example.com" commit --quiet --allow-empty -m "leaked" &> output.txt
+check_result=$?
+grep -qe "Email mismatch - expected=tester@example.com != actual=leaked@example.com (command config command line:)" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+cd ..
+git init --quiet framework_project && cd framework_project || exit
+git config user.name "tester"
+git config user.email "tester@example.com"
+git remote add origin https://github.com/tester/framework_project.git
+
+echo -e "\033[34;40;3m- Register with a hook framework\033[0m"
+set +e
+identity git install --framework lefthook &> output.txt
+identity git --check &>> output.txt
+check_result=$?
+grep -qe "registered with lefthook but its hooks aren't installed" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]] || ! grep -qe "run: identity git hook --pre-commit" lefthook.yml; then
+  cat output.txt lefthook.yml
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+echo -e "#!/bin/sh\n# Moving to lefthook soon\nexit 0\n" > .git/hooks/pre-commit
+
+echo -e "\033[34;40;3m- Reject a hook which only mentions the hook framework\033[0m"
+set +e
+identity git --check &> output.txt
+check_result=$?
+grep -qe "the pre-commit hook wasn't installed by lefthook" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+# What `lefthook install` would do
+echo -e "#!/bin/sh\ncall_lefthook()\n{\n  lefthook \"\$@\"\n}\ncall_lefthook run \"pre-commit\" \"\$@\"\n" > .git/hooks/pre-commit
+
+echo -e "\033[34;40;3m- Recognise a check registered with a hook framework\033[0m"
+set +e
+identity git --check &> output.txt
+check_result=$?
+grep -qe "Everything looks good!" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+mv lefthook.yml lefthook.yml.bak
+echo "pre-commit: {}" > lefthook.yml
+
+echo -e "\033[34;40;3m- Reject a framework hook without the identity check registered\033[0m"
+set +e
+identity git --check &> output.txt
+check_result=$?
+grep -qe "managed by lefthook but the identity check isn't registered with it" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+mv lefthook.yml.bak lefthook.yml
+
+git init --quiet --bare ~/push_remote.git
+mkdir -p ~/work
+cd ~/work
+git init --quiet push_project && cd push_project || exit
+git config user.name "worker"
+git config user.email "worker@example.com"
+git remote add origin ~/push_remote.git
+echo -e '#!/bin/sh\necho "chained pre-push $1"' > .git/hooks/pre-push
+chmod +x .git/hooks/pre-push
+identity git install --pre-push > /dev/null
+git commit --quiet --allow-empty -m "Good commit"
+git commit --quiet --allow-empty --author "intruder <intruder@example.com>" -m "Bad commit"
+
+echo -e "\033[34;40;3m- Reject pushing a commit by another author\033[0m"
+set +e
+git push --quiet origin HEAD:main &> output.txt
+check_result=$?
+grep -qe "has author \`intruder <intruder@example.com>\`" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -eq 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted failure but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]] || git --git-dir ~/push_remote.git rev-parse --quiet --verify main &> /dev/null; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git reset --quiet --hard HEAD~1
+
+echo -e "\033[34;40;3m- Push commits matching the identity and chain the existing hook\033[0m"
+set +e
+git push --quiet origin HEAD:main &> output.txt
+check_result=$?
+grep -qe "chained pre-push origin" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+cd ..
+git init --quiet trailer_project && cd trailer_project || exit
+git config user.name "tester"
+git config user.email "tester@example.com"
+git remote add origin https://github.com/tester/trailer_project.git
+identity git install --commit-msg > /dev/null
+
+echo -e "\033[34;40;3m- Reject signing off as another identity\033[0m"
+set +e
+git commit --quiet --allow-empty -m "Bad sign off" -m "Signed-off-by: tinkerer <tinkerer@example.com>" &> output.txt
+check_result=$?
+grep -qe "Signed-off-by mismatch - expected=tester <tester@example.com> != actual=tinkerer <tinkerer@example.com>" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+echo -e "\033[34;40;3m- Accept signing off as yourself and passing on someone else's sign off\033[0m"
+set +e
+git commit --quiet --allow-empty -m "Good sign off" -m "Signed-off-by: contributor <contributor@example.org>" --signoff &> output.txt
+check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+echo -e "\033[34;40;3m- Rewrite signing off as another identity\033[0m"
+echo "Rewritten sign off" > message.txt
+echo "" >> message.txt
+echo "Signed-off-by: tinkerer <tinkerer@example.com>" >> message.txt
+set +e
+identity git hook --commit-msg --rewrite message.txt &> output.txt
+check_result=$?
+grep -qe "Signed-off-by: tester <tester@example.com>" message.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]] || grep -qe "tinkerer" message.txt; then
+  cat output.txt message.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+cd ..
+git init --quiet hook_mode_project && cd hook_mode_project || exit
+git config user.name "tinkerer"
+git config user.email "tinkerer@example.com"
+git remote add origin https://github.com/tester/hook_mode_project.git
+identity git install > /dev/null
+
+echo -e "\033[34;40;3m- Warn about a mismatch without rejecting the commit\033[0m"
+sed -i 's/identity git hook --pre-commit/identity git hook --pre-commit --mode warn/' .git/hooks/pre-commit
+set +e
+git commit --quiet --allow-empty -m "Warned commit" &> output.txt
+check_result=$?
+grep -qe "Username mismatch - expected=tester != actual=tinkerer" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+identity git install --force > /dev/null
+cp ~/.config/identity.toml ~/.config/identity.toml.bak
+sed -i '/^version/a [git]\nhook_mode = "fix"' ~/.config/identity.toml
+
+echo -e "\033[34;40;3m- Fix a mismatch configured with hook_mode\033[0m"
+set +e
+git commit --quiet --allow-empty -m "Fixed commit" &> output.txt
+check_result=$?
+grep -qe "Switched this repository to identity \`tester\`, run the commit again to use it" output.txt
+content_check_result=$?
+git commit --quiet --allow-empty -m "Fixed commit" &>> output.txt
+retry_result=$?
+set -e
+
+if [[ $check_result -ne 1 || $retry_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 then 0 but got $check_result and $retry_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 || "$(git config user.name)" != "tester" ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+mv ~/.config/identity.toml.bak ~/.config/identity.toml
+
+cd ..
+git init --quiet audit_project && cd audit_project || exit
+git config user.name "tester"
+git config user.email "tester@example.com"
+git remote add origin https://github.com/tester/audit_project.git
+git commit --quiet --allow-empty --author "tinkerer <tinkerer@example.com>" -m "Leaked commit"
+git commit --quiet --allow-empty --author "intruder <intruder@example.com>" -m "Intruding commit"
+git commit --quiet --allow-empty -m "Good commit"
+
+echo -e "\033[34;40;3m- Audit history for commits made with the wrong identity\033[0m"
+set +e
+identity git audit &> output.txt
+check_result=$?
+grep -qe "tinkerer <tinkerer@example.com> (identity \`tinkerer\`) - 1 commit(s)" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]] || ! grep -qe "intruder <intruder@example.com> - 1 commit(s)" output.txt; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+echo -e "\033[34;40;3m- Audit history as JSON\033[0m"
+set +e
+identity git audit --json &> output.txt
+check_result=$?
+grep -qe '"identity": "tinkerer"' output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+echo -e "\033[34;40;3m- Audit history since a revision\033[0m"
+set +e
+identity git audit --since HEAD~1 &> output.txt
+check_result=$?
+grep -qe "All commits match identity \`tester\`" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+cd ..
+git init --quiet fix_history_project && cd fix_history_project || exit
+git config user.name "tester"
+git config user.email "tester@example.com"
+git remote add origin https://github.com/tester/fix_history_project.git
+git commit --quiet --allow-empty --author "tinkerer <tinkerer@example.com>" -m "Pushed leak"
+git commit --quiet --allow-empty --author "contributor <contributor@example.org>" -m "Pushed contribution"
+# Pretend the history so far has been pushed
+git update-ref refs/remotes/origin/main HEAD
+git commit --quiet --allow-empty --author "tinkerer <tinkerer@example.com>" -m "Unpushed leak"
+git commit --quiet --allow-empty --author "contributor <contributor@example.org>" -m "Unpushed contribution"
+git commit --quiet --allow-empty -m "Unpushed good commit"
+head_before="$(git rev-parse HEAD)"
+
+echo -e "\033[34;40;3m- Preview fixing history without changing anything\033[0m"
+set +e
+identity git fix-history --dry-run &> output.txt
+check_result=$?
+grep -qe "Unpushed leak" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 || "$(git rev-parse HEAD)" != "$head_before" || -e .mailmap ]] \
+  || grep -qe "Unpushed contribution" output.txt; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+echo -e "\033[34;40;3m- Fix history made as another identity\033[0m"
+set +e
+identity git fix-history --yes &> output.txt
+check_result=$?
+grep -qe "Rewrote 1 unpushed commit(s) as \`tester\`" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 || "$(git rev-list --count HEAD)" -ne 5 ]] \
+  || [[ "$(git log -1 --format=%ae HEAD~2)" != "tester@example.com" ]] \
+  || [[ "$(git log -1 --format=%ae HEAD~1)" != "contributor@example.org" ]] \
+  || ! grep -qxe "tester <tester@example.com> tinkerer <tinkerer@example.com>" .mailmap \
+  || grep -qe "contributor" .mailmap; then
+  cat output.txt .mailmap
+  git log --format="%h %an <%ae> %cn <%ce> %s"
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git checkout --quiet -b other
+git commit --quiet --allow-empty --author "tinkerer <tinkerer@example.com>" -m "Pushed leak on another branch"
+git update-ref refs/remotes/origin/other HEAD
+git checkout --quiet -
+git commit --quiet --allow-empty --author "tinkerer <tinkerer@example.com>" -m "Unpushed leak before a merge"
+git merge --quiet --no-ff --no-edit other
+
+echo -e "\033[34;40;3m- Fix history without rewriting pushed commits which were merged in\033[0m"
+set +e
+identity git fix-history --yes &> output.txt
+check_result=$?
+grep -qe "Rewrote 1 unpushed commit(s) as \`tester\`" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]] || ! git merge-base --is-ancestor origin/other HEAD \
+  || [[ "$(git log -1 --format=%ae HEAD^1)" != "tester@example.com" ]]; then
+  cat output.txt
+  git log --graph --format="%h %an <%ae> %s"
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+echo -e "\033[34;40;3m- Answer Git as a credential helper\033[0m"
+set +e
+printf 'url=https://github.com/bad-credentials/repo.git\n\n' | git -c credential.helper= -c credential.helper="!identity git credential" -c credential.useHttpPath=true credential fill &> output.txt
+check_result=$?
+grep -qxe "password=the-right-token" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]] || ! grep -qxe "username=bad-credentials" output.txt; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+echo -e "\033[34;40;3m- Refuse to guess between accounts on the same host\033[0m"
+set +e
+(cd ~ && printf 'protocol=https\nhost=github.com\n\n' | identity git credential get) &> output.txt
+check_result=$?
+grep -qe "several accounts match https://github.com" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]] || grep -qe "password=" output.txt; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+cd ..
+git init --quiet remotes_project && cd remotes_project || exit
+git config user.name "tester"
+git config user.email "tester@example.com"
+git remote add origin https://github.com/unknown/remotes_project.git
+git remote add upstream https://github.com/tester/remotes_project.git
+
+echo -e "\033[34;40;3m- Match identity by a remote other than origin\033[0m"
+set +e
+identity git hook --pre-commit &> output.txt
+check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+git remote set-url origin https://github.com/tinkerer/remotes_project.git
+git config user.name "tinkerer"
+git config user.email "tinkerer@example.com"
+identity git install > /dev/null
+
+echo -e "\033[34;40;3m- Report remotes matching different identities\033[0m"
+set +e
+identity git --check &> output.txt
+check_result=$?
+grep -qe "Remotes match different identities - origin=\`tinkerer\`, upstream=\`tester\`, using \`tinkerer\` from \`origin\`" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git checkout --quiet -b tracking
+git config branch.tracking.remote upstream
+
+echo -e "\033[34;40;3m- Prefer the remote the current branch tracks\033[0m"
+set +e
+identity git hook --pre-commit &> output.txt
+check_result=$?
+grep -qe "Username mismatch - expected=tester != actual=tinkerer" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+cd ..
+git init --quiet --bare ~/clone_remote.git
+git config --global url.$HOME/clone_remote.git.insteadOf https://github.com/tester/clone_project.git
+
+echo -e "\033[34;40;3m- Clone as the identity matched for the URL\033[0m"
+set +e
+identity git clone https://github.com/tester/clone_project.git &> output.txt
+check_result=$?
+grep -qe "Cloned into \`clone_project\` as \`tester\`" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 || "$(git -C clone_project config user.email)" != "tester@example.com" ]] \
+  || ! grep -qe "identity git hook --pre-commit" clone_project/.git/hooks/pre-commit; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git config --global --unset url.$HOME/clone_remote.git.insteadOf
+
+mkdir -p ~/checkouts/nested
+for project in tester tinkerer; do
+  git init --quiet ~/checkouts/nested/$project
+  git -C ~/checkouts/nested/$project remote add origin https://github.com/$project/recursive.git
+  git -C ~/checkouts/nested/$project config user.name "tester"
+  git -C ~/checkouts/nested/$project config user.email "tester@example.com"
+done
+git init --quiet ~/checkouts/unknown
+git -C ~/checkouts/unknown remote add origin https://github.com/unknown/recursive.git
+
+echo -e "\033[34;40;3m- Check every repository under a directory\033[0m"
+set +e
+identity git check --recursive ~/checkouts &> output.txt
+check_result=$?
+grep -qE "nested/tinkerer +tinkerer +Pre-commit hook not found, identity mismatch" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]] || ! grep -qE "unknown +fallback \(default\) +Pre-commit hook not found" output.txt; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+cd ~/checkouts/unknown || exit
+git config user.name "not-the-fallback"
+
+echo -e "\033[34;40;3m- Report every failing check with a hint\033[0m"
+set +e
+identity git check &> output.txt
+check_result=$?
+grep -qe "^\[fail\] Pre-commit hook - Pre-commit hook not found" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+# The checks after the first failure still run, each with its own hint
+if [[ $content_check_result -ne 0 ]] \
+  || ! grep -A1 -e "^\[fail\] Pre-commit hook" output.txt | grep -qe "Run \`identity git install\`" \
+  || ! grep -qe "^\[warn\] Identity - No identity matched, using default identity \`fallback\`" output.txt \
+  || ! grep -A1 -e "^\[fail\] Git config - Username mismatch" output.txt | grep -qe "Run \`identity switch --service git\`" \
+  || ! grep -qe "2 of [0-9]* checks failed" output.txt \
+  || grep -qe "Everything looks good!" output.txt; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+cd ~ || exit
+rm -rf ~/checkouts/unknown
+
+echo -e "\033[34;40;3m- Install in every repository under a directory\033[0m"
+set +e
+identity git install --recursive ~/checkouts &> output.txt
+check_result=$?
+identity git check --recursive ~/checkouts &>> output.txt
+recheck_result=$?
+grep -qe "2 repositories, 0 with problems" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 || $recheck_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 and 0 but got $check_result and $recheck_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 || "$(git -C ~/checkouts/nested/tinkerer config user.name)" != "tinkerer" ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+for skipped in .cache/project node_modules/project nested/tester/vendored; do
+  git init --quiet ~/checkouts/$skipped
+done
+mkdir -p ~/checkouts/locked/project
+chmod 000 ~/checkouts/locked
+
+echo -e "\033[34;40;3m- Skip unreadable, hidden and vendored directories\033[0m"
+set +e
+identity git check --recursive ~/checkouts &> output.txt
+check_result=$?
+grep -qe "2 repositories, 0 with problems" output.txt
+content_check_result=$?
+set -e
+
+chmod 755 ~/checkouts/locked
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+# Root can read the directory anyway, in which case there's nothing to warn about
+if [[ $content_check_result -ne 0 ]] \
+  || { [[ "$(id -u)" -ne 0 ]] && ! grep -qe "Skipping directory which can't be read" output.txt; }; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+cd ~ || exit
+git init --quiet ~/backend_project
+git -C ~/backend_project remote add origin tk:backend.git
+git config --global url.https://github.com/tinkerer/.insteadOf tk:
+cat > ~/backend_include << END
+[user]
+  name = "tinkerer" ; set by an include
+  email = tinkerer@example.com
+END
+git config --global includeIf.gitdir:~/backend_project/.path ~/backend_include
+cd ~/backend_project || exit
+
+echo -e "\033[34;40;3m- Read includes and URL rewrites without running Git\033[0m"
+set +e
+identity git check &> output.txt
+check_result=$?
+IDENTITY_GIT_BACKEND=subprocess identity git check &> subprocess_output.txt
+subprocess_check_result=$?
+grep -qe "\[pass\] Identity - Matched \`tinkerer\`" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne $subprocess_check_result ]]; then
+  cat output.txt subprocess_output.txt
+  echo -e "\033[91;40mWrong exit code, wanted $subprocess_check_result but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]] || ! diff output.txt subprocess_output.txt || ! grep -qe "\[pass\] Git config" output.txt; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+echo -e "\033[34;40;3m- Report where each value is set\033[0m"
+set +e
+identity whoami --service git &> output.txt
+check_result=$?
+IDENTITY_GIT_BACKEND=subprocess identity whoami --service git &> subprocess_output.txt
+grep -qe "user.name  = tinkerer (global config $HOME/backend_include)" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]] || ! diff output.txt subprocess_output.txt; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+cat > ~/hasconfig_include << END
+[core]
+  sshCommand = ssh -o BatchMode=yes
+END
+# Git matches the URL as it's written, before `insteadOf` rewrites it
+git config --global "includeIf.hasconfig:remote.*.url:tk:*.path" ~/hasconfig_include
+echo -e "\033[34;40;3m- Read hasconfig includes without running Git\033[0m"
+set +e
+PATH="$HOME/broken_git:$PATH" identity whoami --service git &> output.txt
+check_result=$?
+IDENTITY_GIT_BACKEND=subprocess identity whoami --service git &> subprocess_output.txt
+grep -qe "core.sshCommand = ssh -o BatchMode=yes (global config $HOME/hasconfig_include)" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]] || ! diff output.txt subprocess_output.txt; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git config --global --unset "includeIf.hasconfig:remote.*.url:tk:*.path"
+git config user.email "leaked@example.com"
+
+echo -e "\033[34;40;3m- Report where a mismatched value is set\033[0m"
+set +e
+identity git check &> output.txt
+check_result=$?
+grep -qe "Email mismatch - expected=tinkerer@example.com != actual=leaked@example.com (local config $HOME/backend_project/.git/config)" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git config --unset user.email
+
+echo -e "\033[34;40;3m- Only switch Git config scopes\033[0m"
+set +e
+identity switch --service cargo --identity tinkerer --scope global &> output.txt
+check_result=$?
+grep -qe "\`--scope\` is only supported for the git service" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git config --global --unset includeIf.gitdir:~/backend_project/.path
+git config --global --unset url.https://github.com/tinkerer/.insteadOf
+
+cd ~ || exit
+git init --quiet ~/submodule_source
+git -C ~/submodule_source -c user.name=tinkerer -c user.email=tinkerer@example.com commit --quiet --allow-empty -m "Initial"
+git init --quiet ~/super_project && cd ~/super_project || exit
+git remote add origin https://github.com/tester/super.git
+git config user.name "tester"
+git config user.email "tester@example.com"
+git -c protocol.file.allow=always submodule --quiet add ~/submodule_source libs/sub
+git -C libs/sub remote set-url origin https://github.com/tinkerer/sub.git
+
+echo -e "\033[34;40;3m- Check each submodule against its own identity\033[0m"
+set +e
+identity git check &> output.txt
+check_result=$?
+grep -qe "\[fail\] Submodule - \`libs/sub\` should use \`tinkerer\` - Username mismatch - expected=tinkerer != actual=" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git -C libs/sub config user.name "tinkerer"
+git -C libs/sub config user.email "tinkerer@example.com"
+
+echo -e "\033[34;40;3m- Install in each submodule\033[0m"
+set +e
+identity git install --recurse-submodules &> output.txt
+check_result=$?
+identity git check &>> output.txt
+recheck_result=$?
+grep -qe "\[pass\] Submodule - \`libs/sub\` uses \`tinkerer\`" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 || $recheck_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 and 0 but got $check_result and $recheck_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]] || ! grep -qe "Submodule \`libs/sub\`" output.txt; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+echo -e "\033[34;40;3m- Keep submodules out of the global config\033[0m"
+set +e
+identity switch --service git --scope global --recurse-submodules &> output.txt
+check_result=$?
+grep -qe "\`--recurse-submodules\` can't be used with \`--scope global\`" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 1 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 1 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
@@ -0,0 +1,240 @@
+#!/usr/bin/env bash
+
+set -e
+
+echo -e "\033[34;40;3mTesting - identity shell-init, prompt and completions\033[0m"
+
+mkdir -p ~/.config/
+touch ~/.config/identity.toml
+
+cat > ~/.config/identity.toml << END
+version = "1.0"
+
+[[identity]]
+id = "tester"
+email = "tester@example.com"
+
+[[identity.account]]
+service = "git"
+user = "tester"
+match_url = "https://github.com/tester/*"
+
+END
+
+trap "popd > /dev/null" EXIT
+pushd "$(mktemp -d)" > /dev/null || exit
+
+git init --quiet shell_project
+git -C shell_project config user.name "tester"
+git -C shell_project config user.email "not-the-tester@example.com"
+git -C shell_project remote add origin https://github.com/tester/project.git
+
+echo -e "\033[34;40;3m- Warns about a mismatch when entering a repository\033[0m"
+set +e
+(cd shell_project && identity git hook --chpwd) > /dev/null 2> output.txt
+check_result=$?
+grep -qe "this repository should use \`tester\`" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+echo -e "\033[34;40;3m- Applies the identity from the bash hook\033[0m"
+set +e
+bash -c 'eval "$(identity shell-init bash --apply)" && cd shell_project && __identity_hook' &> output.txt
+check_result=$?
+grep -qe "switched to \`tester\`" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 || "$(git -C shell_project config user.email)" != "tester@example.com" ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+echo -e "\033[34;40;3m- Prompt shows the matched identity\033[0m"
+set +e
+(cd shell_project && identity prompt) &> output.txt
+check_result=$?
+grep -qxe "tester" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+echo -e "\033[34;40;3m- Prompt shows a mismatch after the Git config changes\033[0m"
+set +e
+git -C shell_project config user.email "not-the-tester@example.com"
+(cd shell_project && identity prompt) &> output.txt
+check_result=$?
+grep -qxe "!tester" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git -C shell_project config --unset user.email
+git config --global user.email "tester@example.com"
+(cd shell_project && identity prompt) > /dev/null
+git config --global user.email "not-the-tester@example.com"
+
+echo -e "\033[34;40;3m- Prompt notices changes to the global Git config\033[0m"
+set +e
+(cd shell_project && identity prompt) &> output.txt
+check_result=$?
+grep -qxe "!tester" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+git config --global --unset user.email
+git -C shell_project config user.email "tester@example.com"
+cat >> ~/.config/identity.toml << END
+
+[[identity]]
+id = "other"
+email = "other@example.com"
+
+[[identity.account]]
+service = "git"
+user = "other"
+match_url = "https://github.com/other/*"
+END
+git -C shell_project commit --quiet --allow-empty -m "First commit"
+git -C shell_project remote add other https://github.com/other/project.git
+git -C shell_project branch other-branch
+git -C shell_project config branch.other-branch.remote other
+git -C shell_project worktree add --quiet --detach ../shell_worktree
+(cd shell_worktree && identity prompt) > /dev/null
+git -C shell_worktree switch --quiet other-branch
+
+echo -e "\033[34;40;3m- Prompt notices a worktree switching to a branch with another remote\033[0m"
+set +e
+(cd shell_worktree && identity prompt) &> output.txt
+check_result=$?
+grep -qxe "!other" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+cp ~/.config/identity.toml identity.toml.bak
+echo "not valid toml" >> ~/.config/identity.toml
+
+echo -e "\033[34;40;3m- Prompt stays quiet when the config is broken\033[0m"
+set +e
+(cd shell_project && identity prompt) &> output.txt
+check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ -s output.txt ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+mv identity.toml.bak ~/.config/identity.toml
+
+echo -e "\033[34;40;3m- Prompt remembers a lookup which timed out\033[0m"
+set +e
+(cd shell_project && IDENTITY_GIT_BACKEND=subprocess identity prompt --timeout 0) &> output.txt
+check_result=$?
+(cd shell_project && identity prompt) &>> output.txt
+recheck_result=$?
+set -e
+
+if [[ $check_result -ne 0 || $recheck_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 and 0 but got $check_result and $recheck_result\033[0m"
+  exit 1
+fi
+
+if [[ -s output.txt ]] || ! grep -qe "expires" ~/.cache/identity/prompt.toml; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi
+
+echo -e "\033[34;40;3m- Completions include identity ids\033[0m"
+set +e
+identity completions bash &> output.txt
+check_result=$?
+grep -qe "tester" output.txt
+content_check_result=$?
+set -e
+
+if [[ $check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong exit code, wanted 0 but got $check_result\033[0m"
+  exit 1
+fi
+
+if [[ $content_check_result -ne 0 ]]; then
+  cat output.txt
+  echo -e "\033[91;40mWrong content\033[0m"
+  exit 1
+fi